@@ -36,22 +36,29 @@ if [[ $OSSYS = macos ]] ; then
   INSTALLER=homebrew
   APP_INSTALLER=homebrew-apps
 elif [[ $OSSYS = linux ]] ; then
-  STDOUT=$(yum --version 2>&1)
-  if [[ $? -eq 0 ]] ; then
+  if command -v flatpak >/dev/null 2>&1 ; then
+    APP_INSTALLER=flatpak
+  elif command -v snap >/dev/null 2>&1 ; then
+    APP_INSTALLER=snap
+  fi
+  if command -v apt-get >/dev/null 2>&1 ; then
+    INSTALLER=apt
+  elif command -v dnf >/dev/null 2>&1 ; then
+    INSTALLER=dnf
+  elif command -v yum >/dev/null 2>&1 ; then
     INSTALLER=yum
-    APP_INSTALLER=flatpack
+  elif command -v pacman >/dev/null 2>&1 ; then
+    INSTALLER=pacman
+  elif command -v zypper >/dev/null 2>&1 ; then
+    INSTALLER=zypper
+  elif command -v apk >/dev/null 2>&1 ; then
+    INSTALLER=apk
   else
-    STDOUT=$(apt --version 2>&1)
-    if [[ $? -eq 0 ]] ; then
-      INSTALLER=apt
-      APP_INSTALLER=snap
+    if [[ "$OSDIST" == "" ]] ; then
+      echo "No known installer for $OSSYS-$OSVERSION-$OSARCH" >&2
     else
-      if [[ "$OSDIST" == "" ]] ; then
-        echo "No known installer for $OSSYS-$OSVERSION-$OSARCH" >&2
-      else
-        echo "No known installer for $OSSYS-$OSVERSION-$OSARCH-$OSDIST" >&2
-      fi
-      exit 1
+      echo "No known installer for $OSSYS-$OSVERSION-$OSARCH-$OSDIST" >&2
     fi
+    exit 1
   fi
 fi
@@ -0,0 +1,628 @@
+/*!
+The behaviour behind each installer, as a trait that custom installer logic may implement.
+
+Every installer named in the configuration is, by default, backed by a `CommandBackend` which
+runs the command and arguments described in `installers.toml`. A `Registry` maps installer
+names to backends and is consulted whenever an inner package is installed, updated, or deleted;
+a backend added with `ConfigurationBuilder::backend` replaces the default for that name, and a
+`ScriptBackend` runs a user-provided script in place of a package manager.
+
+A custom backend still needs an entry in the installer configuration, as that is where the
+platforms it supports are described.
+
+# Script Installers
+
+An installer with `kind = "script"` is backed by a `ScriptBackend`, so that an in-house
+installer can be added with configuration alone; a relative `script` path is relative to the
+configuration file it is set in.
+
+```toml
+[tarball]
+name = "Internal Tarballs"
+kind = "script"
+script = "scripts/tarball.sh"
+platforms = ["linux", "macos"]
+```
+
+The script is run as `script <action> [<name> [<argument>..]]` where the action is one of
+`install`, `update`, `delete`, `is-installed`, `list`, or `version`, and the name and arguments
+are those of the inner package. The same values are in the environment variables
+`PACKPACK_ACTION`, `PACKPACK_PACKAGE`, and, if a version is requested, `PACKPACK_VERSION`; and
+the key the installer is configured under, `tarball` above, is in `PACKPACK_INSTALLER`.
+
+An exit status of zero is success. The script may also report back on standard output with
+lines of the form `<keyword> <value>`, any other line is ignored:
+
+* `installed yes` or `installed no`, in answer to `is-installed`; without one an exit status of
+  `0` means installed, `1` not installed, and any other that the script cannot tell.
+* `package <name>`, once for each installed package, in answer to `list`.
+* `version <version>`, in answer to `version`.
+* `info <message>`, logged as progress.
+* `error <message>`, used as the reason for failure in place of standard error.
+
+# Example
+
+```rust,no_run
+use packpack::backend::ScriptBackend;
+use packpack::config::ConfigurationBuilder;
+use std::sync::Arc;
+
+let config = ConfigurationBuilder::new()
+    .with_user_config()
+    .backend(
+        "dotfiles".parse().unwrap(),
+        Arc::new(ScriptBackend::new("dotfiles".parse().unwrap(), "/usr/local/bin/dotfiles-installer")),
+    )
+    .build()
+    .unwrap();
+```
+
+*/
+
+use crate::config::installers::{substitute, Installer, InstallerKind};
+use crate::config::Configuration;
+use crate::error::{ErrorKind, Result};
+use crate::name::Name;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::Arc;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// What a backend is asked to act on; `name` is the name this installer knows the package by,
+/// and `arguments` are any additional arguments from the inner package.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstallRequest {
+    pub name: String,
+    pub version: Option<String>,
+    pub arguments: Vec<String>,
+}
+
+///
+/// The logic behind an installer. Only `is_available`, `install`, `update`, and `delete` are
+/// required; a backend that cannot check, list, or report versions returns `None`.
+///
+pub trait InstallerBackend: Debug + Send + Sync {
+    /// Is this backend usable on this machine.
+    fn is_available(&self) -> bool;
+
+    fn install(&self, request: &InstallRequest) -> Result<()>;
+
+    fn update(&self, request: &InstallRequest) -> Result<()>;
+
+    fn delete(&self, request: &InstallRequest) -> Result<()>;
+
+    /// Is the requested package already installed, or `None` if this backend cannot tell.
+    fn is_installed(&self, _request: &InstallRequest) -> Option<bool> {
+        None
+    }
+
+    /// The packages this backend has installed, or `None` if it cannot list them.
+    fn list(&self) -> Result<Option<Vec<String>>> {
+        Ok(None)
+    }
+
+    /// The installed version of the requested package, or `None` if this backend cannot tell.
+    fn version(&self, _request: &InstallRequest) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// A short description of the action, used when it is skipped in a dry run.
+    fn describe(&self, action: &str, request: &InstallRequest) -> String {
+        format!("{} {}", action, request.name)
+    }
+}
+
+///
+/// The default backend, driven entirely by an installer's configuration.
+///
+#[derive(Clone, Debug)]
+pub struct CommandBackend {
+    installer: Installer,
+}
+
+///
+/// A backend that runs a script implementing the protocol described in the module
+/// documentation.
+///
+#[derive(Clone, Debug)]
+pub struct ScriptBackend {
+    name: Name,
+    script: PathBuf,
+    requires: Vec<String>,
+}
+
+///
+/// The backend for each installer name.
+///
+#[derive(Clone, Debug, Default)]
+pub struct Registry {
+    backends: HashMap<Name, Arc<dyn InstallerBackend>>,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// What a script reported, from its exit status and the keyword lines of its output.
+///
+#[derive(Debug, Default)]
+struct ScriptResponse {
+    status: Option<i32>,
+    installed: Option<bool>,
+    packages: Vec<String>,
+    version: Option<String>,
+    errors: Vec<String>,
+    stderr: String,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl InstallRequest {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: None,
+            arguments: Default::default(),
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl CommandBackend {
+    pub fn new(installer: Installer) -> Self {
+        Self { installer }
+    }
+
+    pub fn installer(&self) -> &Installer {
+        &self.installer
+    }
+
+    fn run(&self, action: &str, template: &[String], request: &InstallRequest) -> Result<()> {
+        if template.is_empty() {
+            return Err(ErrorKind::UnsupportedInstallerAction(
+                self.installer.name.clone(),
+                action.to_string(),
+            )
+            .into());
+        }
+        let arguments = self.arguments(template, request);
+        trace!("> > > `{} {:?}`", self.installer.command, arguments);
+        check_output(
+            &self.installer.name,
+            self.installer.command(&arguments).output()?,
+        )
+    }
+
+    fn arguments(&self, template: &[String], request: &InstallRequest) -> Vec<String> {
+        let mut arguments = substitute(template, &self.package_argument(request));
+        arguments.extend(request.arguments.iter().cloned());
+        arguments
+    }
+
+    fn package_argument(&self, request: &InstallRequest) -> String {
+        self.installer
+            .package_argument(&request.name, request.version.as_deref())
+    }
+}
+
+impl InstallerBackend for CommandBackend {
+    fn is_available(&self) -> bool {
+        self.installer.is_available()
+    }
+
+    fn install(&self, request: &InstallRequest) -> Result<()> {
+        self.run("install", &self.installer.install_arguments, request)
+    }
+
+    fn update(&self, request: &InstallRequest) -> Result<()> {
+        self.run("update", &self.installer.update_arguments, request)
+    }
+
+    fn delete(&self, request: &InstallRequest) -> Result<()> {
+        self.run("delete", &self.installer.delete_arguments, request)
+    }
+
+    fn is_installed(&self, request: &InstallRequest) -> Option<bool> {
+        // checks take the bare name, `pip show black` rather than `pip show black==22.1`.
+        self.installer.is_installed(&request.name)
+    }
+
+    fn list(&self) -> Result<Option<Vec<String>>> {
+        Ok(self.installer.list_installed()?)
+    }
+
+    fn describe(&self, action: &str, request: &InstallRequest) -> String {
+        let template = match action {
+            "install" => &self.installer.install_arguments,
+            "update" => &self.installer.update_arguments,
+            _ => &self.installer.delete_arguments,
+        };
+        format!(
+            "{} {:?}",
+            self.installer.command,
+            self.arguments(template, request)
+        )
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl ScriptBackend {
+    pub fn new<P: AsRef<Path>>(name: Name, script: P) -> Self {
+        Self {
+            name,
+            script: script.as_ref().to_path_buf(),
+            requires: Default::default(),
+        }
+    }
+
+    ///
+    /// The backend for an installer of kind `script`, registered under the configuration key
+    /// `name`, or `None` if it has no script.
+    ///
+    pub fn from_installer(name: &Name, installer: &Installer) -> Option<Self> {
+        installer.script.as_ref().map(|script| Self {
+            name: name.clone(),
+            script: script.clone(),
+            requires: installer.requires.clone(),
+        })
+    }
+
+    pub fn script(&self) -> &Path {
+        &self.script
+    }
+
+    fn call(&self, action: &str, request: Option<&InstallRequest>) -> Result<ScriptResponse> {
+        let mut command = Command::new(&self.script);
+        let _ = command
+            .arg(action)
+            .env("PACKPACK_ACTION", action)
+            .env("PACKPACK_INSTALLER", self.name.to_string())
+            .stdin(Stdio::null());
+        if let Some(request) = request {
+            let _ = command
+                .arg(&request.name)
+                .args(&request.arguments)
+                .env("PACKPACK_PACKAGE", &request.name);
+            if let Some(version) = &request.version {
+                let _ = command.env("PACKPACK_VERSION", version);
+            }
+        }
+        trace!("> > > {:?}", command);
+        Ok(ScriptResponse::from(command.output()?))
+    }
+
+    fn run(&self, action: &str, request: &InstallRequest) -> Result<()> {
+        let response = self.call(action, Some(request))?;
+        if response.is_success() {
+            Ok(())
+        } else {
+            Err(ErrorKind::InstallerCommandFailed(self.name.clone(), response.message()).into())
+        }
+    }
+}
+
+impl InstallerBackend for ScriptBackend {
+    fn is_available(&self) -> bool {
+        self.script.is_file()
+            && self
+                .requires
+                .iter()
+                .all(|command| which::which(command).is_ok())
+    }
+
+    fn install(&self, request: &InstallRequest) -> Result<()> {
+        self.run("install", request)
+    }
+
+    fn update(&self, request: &InstallRequest) -> Result<()> {
+        self.run("update", request)
+    }
+
+    fn delete(&self, request: &InstallRequest) -> Result<()> {
+        self.run("delete", request)
+    }
+
+    fn is_installed(&self, request: &InstallRequest) -> Option<bool> {
+        let response = self.call("is-installed", Some(request)).ok()?;
+        match (response.installed, response.status) {
+            (Some(installed), _) => Some(installed),
+            (None, Some(0)) => Some(true),
+            (None, Some(1)) => Some(false),
+            _ => None,
+        }
+    }
+
+    fn list(&self) -> Result<Option<Vec<String>>> {
+        let response = self.call("list", None)?;
+        Ok(if response.is_success() {
+            Some(response.packages)
+        } else {
+            None
+        })
+    }
+
+    fn version(&self, request: &InstallRequest) -> Result<Option<String>> {
+        let response = self.call("version", Some(request))?;
+        Ok(if response.is_success() {
+            response.version
+        } else {
+            None
+        })
+    }
+
+    fn describe(&self, action: &str, request: &InstallRequest) -> String {
+        format!("{:?} {} {}", self.script, action, request.name)
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl From<Output> for ScriptResponse {
+    fn from(output: Output) -> Self {
+        let mut response = ScriptResponse {
+            status: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ..Default::default()
+        };
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let (keyword, value) = match line.trim().split_once(' ') {
+                Some((keyword, value)) => (keyword, value.trim()),
+                None => (line.trim(), ""),
+            };
+            match keyword {
+                "installed" => response.installed = Some(matches!(value, "yes" | "true")),
+                "package" if !value.is_empty() => response.packages.push(value.to_string()),
+                "version" if !value.is_empty() => response.version = Some(value.to_string()),
+                "info" => info!("> > > {}", value),
+                "error" => response.errors.push(value.to_string()),
+                _ => trace!("> > > ignoring script output {:?}", line),
+            }
+        }
+        response
+    }
+}
+
+impl ScriptResponse {
+    fn is_success(&self) -> bool {
+        self.status == Some(0)
+    }
+
+    fn message(&self) -> String {
+        if self.errors.is_empty() {
+            self.stderr.clone()
+        } else {
+            self.errors.join("; ")
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Registry {
+    ///
+    /// A registry with a `CommandBackend`, or a `ScriptBackend` for those of kind `script`,
+    /// for each configured installer, replaced by any backend added to the configuration with
+    /// `ConfigurationBuilder::backend`.
+    ///
+    pub fn for_config(config: &Configuration) -> Self {
+        let mut registry = Self::default();
+        for (name, installer) in config.installers() {
+            let backend: Arc<dyn InstallerBackend> = match installer.kind {
+                InstallerKind::Script => match ScriptBackend::from_installer(name, installer) {
+                    Some(backend) => Arc::new(backend),
+                    None => continue,
+                },
+                InstallerKind::Command => Arc::new(CommandBackend::new(installer.clone())),
+            };
+            registry.register(name.clone(), backend);
+        }
+        for (name, backend) in config.backends() {
+            registry.register(name.clone(), backend.clone());
+        }
+        registry
+    }
+
+    pub fn register(&mut self, name: Name, backend: Arc<dyn InstallerBackend>) {
+        let _ = self.backends.insert(name, backend);
+    }
+
+    pub fn get(&self, name: &Name) -> Option<&dyn InstallerBackend> {
+        self.backends.get(name).map(|backend| backend.as_ref())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &Name> {
+        self.backends.keys()
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+fn check_output(installer: &Name, output: Output) -> Result<()> {
+    if output.status.success() {
+        Ok(())
+    } else {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(ErrorKind::InstallerCommandFailed(installer.clone(), message).into())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use crate::testing::temp_dir;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+    #[cfg(unix)]
+    use std::os::unix::fs::PermissionsExt;
+
+    #[cfg(unix)]
+    fn write_script(path: &Path) -> PathBuf {
+        let script = path.join("installer.sh");
+        std::fs::write(
+            &script,
+            "#!/bin/sh\n\
+             echo \"info $PACKPACK_INSTALLER $PACKPACK_ACTION\"\n\
+             case \"$1\" in\n\
+               install) [ \"$2\" = good ] || { echo \"error no tarball for $2\"; exit 3; } ;;\n\
+               is-installed) [ \"$PACKPACK_PACKAGE\" = good ] && echo 'installed yes' || echo 'installed no' ;;\n\
+               list) echo 'package good'; echo 'not a keyword'; echo \"package $PACKPACK_INSTALLER\" ;;\n\
+               version) echo \"version ${PACKPACK_VERSION:-1.0}\" ;;\n\
+               *) exit 2 ;;\n\
+             esac\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        script
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_script_backend() {
+        let temp = temp_dir();
+        let script = write_script(temp.path());
+
+        let backend = ScriptBackend::new("script".parse().unwrap(), &script);
+        let good = InstallRequest::new("good");
+        let bad = InstallRequest::new("bad");
+        assert!(backend.is_available());
+        assert!(backend.install(&good).is_ok());
+        assert_eq!(
+            backend.install(&bad).unwrap_err().to_string(),
+            ErrorKind::InstallerCommandFailed(
+                "script".parse().unwrap(),
+                "no tarball for bad".to_string()
+            )
+            .to_string()
+        );
+        assert!(backend.update(&good).is_err());
+        assert_eq!(backend.is_installed(&good), Some(true));
+        assert_eq!(backend.is_installed(&bad), Some(false));
+        assert_eq!(
+            backend.list().unwrap(),
+            Some(vec!["good".to_string(), "script".to_string()])
+        );
+        let versioned = InstallRequest {
+            version: Some("2.1".to_string()),
+            ..InstallRequest::new("good")
+        };
+        assert_eq!(
+            backend.version(&versioned).unwrap(),
+            Some("2.1".to_string())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_script_installer_config() {
+        let temp = temp_dir();
+        let path = temp.path();
+        let _ = write_script(path);
+        let installers = path.join("installers.toml");
+        std::fs::write(
+            &installers,
+            "[tarball]\nname = \"Tarball\"\nkind = \"script\"\nscript = \"installer.sh\"\n",
+        )
+        .unwrap();
+        let config = crate::config::ConfigurationBuilder::new()
+            .installers_from_file(&installers)
+            .build()
+            .unwrap();
+        let tarball: Name = "tarball".parse().unwrap();
+        assert_eq!(
+            config.installer(&tarball).unwrap().script,
+            Some(path.join("installer.sh"))
+        );
+        let registry = Registry::for_config(&config);
+        let backend = registry.get(&tarball).unwrap();
+        assert!(backend.is_available());
+        assert!(backend.install(&InstallRequest::new("good")).is_ok());
+        assert_eq!(
+            backend.list().unwrap(),
+            Some(vec!["good".to_string(), "tarball".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_script_installer_needs_script() {
+        let result = crate::config::ConfigurationBuilder::new()
+            .installers_from_str("[tarball]\nname = \"Tarball\"\nkind = \"script\"\n")
+            .build();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_command_backend_unsupported_action() {
+        let installer: Installer =
+            toml::from_str("name = \"true\"\ncommand = \"true\"\ninstall_arguments = [\"{}\"]\n")
+                .unwrap();
+        let backend = CommandBackend::new(installer);
+        let request = InstallRequest::new("fd");
+        assert!(backend.install(&request).is_ok());
+        assert_eq!(
+            backend.delete(&request).unwrap_err().to_string(),
+            ErrorKind::UnsupportedInstallerAction("true".parse().unwrap(), "delete".to_string())
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_command_backend_checks_bare_name() {
+        let installer: Installer = toml::from_str(
+            "name = \"test\"\ncommand = \"test\"\ncheck_arguments = [\"{}\", \"=\", \"black\"]\n\
+             version_format = \"{name}=={version}\"\n",
+        )
+        .unwrap();
+        let backend = CommandBackend::new(installer);
+        let request = InstallRequest {
+            version: Some("22.1".to_string()),
+            ..InstallRequest::new("black")
+        };
+        assert_eq!(backend.is_installed(&request), Some(true));
+        assert_eq!(
+            backend.is_installed(&InstallRequest::new("white")),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn test_registry_replaces_default() {
+        let cargo: Name = "cargo".parse().unwrap();
+        let rustup: Name = "rustup".parse().unwrap();
+        let config = crate::config::ConfigurationBuilder::new()
+            .backend(
+                cargo.clone(),
+                Arc::new(ScriptBackend::new(cargo.clone(), "/no/such/script")),
+            )
+            .build()
+            .unwrap();
+        let mut registry = Registry::for_config(&config);
+        assert!(!registry.get(&cargo).unwrap().is_available());
+        assert!(registry.get(&rustup).is_some());
+        assert!(registry.get(&"unknown".parse().unwrap()).is_none());
+
+        registry.register(
+            rustup.clone(),
+            Arc::new(ScriptBackend::new(rustup.clone(), "/no/such/script")),
+        );
+        assert!(!registry.get(&rustup).unwrap().is_available());
+    }
+}
@@ -2,24 +2,30 @@
 #[macro_use]
 extern crate log;
 
-use dfpm::config::{current_configuration, LayerKind};
-use dfpm::name::Name;
-use dfpm::package::{get_packages, Package};
-use dfpm::{Installable, Options};
-use serde::export::Formatter;
+use packpack::config::values::{get_value, set_value, show_config};
+use packpack::config::{default_config_dir, ConfigKind, ConfigurationBuilder, LayerKind};
+use packpack::error::{ErrorKind, Location};
+use packpack::name::{Name, NamePattern, QualifiedName, QualifiedPattern};
+use packpack::package::{
+    add_inner_package, check_inner_package_removable, create_package, remove_inner_package,
+    validate_packages, InnerPackage, InnerPackagePriority, InstallerChain, Package,
+};
+use packpack::report::{render, ConfigReport, PackagesReport, ProblemsReport};
+use packpack::selection::{self, Selection};
+use packpack::style::{ColorChoice, Styler};
+use packpack::sync::Repository;
+use packpack::{Installable, Options, OutputFormat};
+use serde::Serialize;
+use std::convert::TryFrom;
 use std::error::Error;
 use std::fmt::Display;
+use std::fmt::Formatter;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use structopt::StructOpt;
 use text_trees::StringTreeNode;
 
-#[derive(Clone, Debug)]
-enum ConfigKind {
-    Platform,
-    Layers,
-    Installers,
-}
-
 #[derive(Clone, Debug)]
 enum Scope {
     Package,
@@ -37,77 +43,200 @@ struct CommandLine {
     #[structopt(long, short)]
     dry_run: bool,
 
+    /// Directory to load configuration files from, overrides $PACKPACK_CONFIG
+    #[structopt(long, parse(from_os_str))]
+    config_dir: Option<PathBuf>,
+
+    /// Directory containing package definitions, overrides $PACKPACK_HOME; may be repeated, and
+    /// packages in later directories override those of the same name in earlier ones
+    #[structopt(long, parse(from_os_str), number_of_values = 1)]
+    packages: Vec<PathBuf>,
+
+    /// Output format for read-only commands: 'text', 'json', 'toml', 'yaml'
+    #[structopt(long, short, default_value = "text")]
+    output: OutputFormat,
+
+    /// When to use color in text output: 'auto', 'always', 'never'; 'auto' honours $NO_COLOR
+    #[structopt(long, default_value = "auto")]
+    color: ColorChoice,
+
     #[structopt(subcommand)]
     cmd: SubCommand,
 }
 
+#[derive(Debug, StructOpt)]
+enum ConfigCommand {
+    /// Show the effective configuration, noting where each value was set
+    Show {
+        /// The configuration to show: 'platform', 'layers', 'installers'
+        #[structopt(name = "KIND")]
+        kind: Option<ConfigKind>,
+
+        /// Output format: 'toml', 'json', 'yaml'; overrides --output for this command
+        #[structopt(long, short)]
+        format: Option<OutputFormat>,
+    },
+    /// Get the effective value at a path such as 'installers.homebrew.command'
+    Get {
+        #[structopt(name = "PATH")]
+        path: String,
+    },
+    /// Set a value at a path in the user configuration file, preserving comments
+    Set {
+        #[structopt(name = "PATH")]
+        path: String,
+
+        #[structopt(name = "VALUE")]
+        value: String,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+struct SelectionArgs {
+    /// Restrict names to one scope: 'package', 'layer', 'all'; by default each name may be
+    /// either a layer or a package
+    #[structopt(long, short)]
+    scope: Option<Scope>,
+
+    /// Only select packages with this tag, may be repeated
+    #[structopt(long = "tag", short = "t", number_of_values = 1)]
+    tags: Vec<Name>,
+
+    /// Only act on inner packages matching this pattern, may be repeated
+    #[structopt(long, number_of_values = 1)]
+    only: Vec<NamePattern>,
+
+    /// The packages, or layers, to act upon; a package may be qualified as 'layer/package'
+    /// or 'package:inner', and any part may be a glob pattern such as 'dev-*'
+    #[structopt(name = "NAME")]
+    names: Vec<QualifiedPattern>,
+}
+
 #[derive(Debug, StructOpt)]
 enum SubCommand {
     /// Show the current configuration settings
-    Config,
+    Config {
+        #[structopt(subcommand)]
+        cmd: Option<ConfigCommand>,
+    },
     /// Install a meta-package
     Install {
-        /// Installer scope: 'package', 'layer', 'all'
-        #[structopt(long, short)]
-        scope: Scope,
-
-        /// The package, or layer, to act upon
-        #[structopt(name = "NAME")]
-        names: Vec<Name>,
+        #[structopt(flatten)]
+        selection: SelectionArgs,
     },
     /// Update an installed meta-package
     Update {
-        /// Installer scope: 'platform', 'layer', 'all'
-        #[structopt(long, short)]
-        scope: Scope,
-
-        /// The package, or layer, to act upon
-        #[structopt(name = "NAME")]
-        names: Vec<Name>,
+        #[structopt(flatten)]
+        selection: SelectionArgs,
     },
     /// Delete an installed meta-package
     Delete {
-        /// Installer scope: 'platform', 'layer', 'all'
-        #[structopt(long, short)]
-        scope: Scope,
-
-        /// The package, or layer, to act upon
-        #[structopt(name = "NAME")]
-        names: Vec<Name>,
+        #[structopt(flatten)]
+        selection: SelectionArgs,
     },
-    /// Inspect meta-package definition file
+    /// Inspect meta-package definition files, all packages if none are selected
     Inspect {
-        /// The package to act upon
+        #[structopt(flatten)]
+        selection: SelectionArgs,
+    },
+    /// Search package names, descriptions, tags, and inner package names for text
+    Search {
+        /// Only search packages with this tag, may be repeated
+        #[structopt(long = "tag", short = "t", number_of_values = 1)]
+        tags: Vec<Name>,
+
+        /// The text to search for, without regard to case
+        #[structopt(name = "TEXT")]
+        text: String,
+    },
+    /// Validate all meta-package definition files, reporting every problem found
+    Validate,
+    /// Create a new meta-package, with a starter definition file, in the package directory
+    New {
+        /// The name of the new package
         #[structopt(name = "NAME")]
-        package: Option<Name>,
+        name: Name,
+
+        /// The inner layer the package belongs to
+        #[structopt(long, short)]
+        layer: Name,
+
+        /// Add an inner package, of the same name, for this installer
+        #[structopt(long, short)]
+        installer: Option<Name>,
+
+        /// Also write stub scripts to run before and after installation
+        #[structopt(long)]
+        with_scripts: bool,
     },
-}
+    /// Add an inner package to a meta-package's definition file
+    Add {
+        /// The meta-package to add to
+        #[structopt(name = "PACKAGE")]
+        package: Name,
 
-impl Display for ConfigKind {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                ConfigKind::Platform => "platform",
-                ConfigKind::Layers => "layers",
-                ConfigKind::Installers => "installers",
-            }
-        )
-    }
-}
+        /// The name of the inner package, as known to the installer
+        #[structopt(name = "INNER")]
+        inner: Name,
 
-impl FromStr for ConfigKind {
-    type Err = String;
+        /// The installer used for the inner package, may be repeated to list fallbacks tried in order
+        #[structopt(long, short, required = true, number_of_values = 1)]
+        installer: Vec<Name>,
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "platform" => Ok(ConfigKind::Platform),
-            "layers" => Ok(ConfigKind::Layers),
-            "installers" => Ok(ConfigKind::Installers),
-            _ => Err(format!("invalid value '{}' for config kind", s)),
-        }
-    }
+        /// The priority of the inner package: 'lowest', 'lower', 'normal', 'higher', 'highest'
+        #[structopt(long, default_value = "normal")]
+        priority: InnerPackagePriority,
+
+        /// The inner package is an application, installed with the app installer
+        #[structopt(long)]
+        app: bool,
+
+        /// Only install on this platform, may be repeated
+        #[structopt(long = "platform", number_of_values = 1)]
+        platforms: Vec<Name>,
+
+        /// An additional argument passed to the installer, may be repeated
+        #[structopt(long = "arg", number_of_values = 1, allow_hyphen_values = true)]
+        additional_arguments: Vec<String>,
+
+        /// The version to install, written as the installer expects it
+        #[structopt(long)]
+        version: Option<String>,
+
+        /// Install the inner package immediately
+        #[structopt(long)]
+        now: bool,
+    },
+    /// Remove an inner package from a meta-package's definition file
+    Remove {
+        /// The meta-package to remove from
+        #[structopt(name = "PACKAGE")]
+        package: Name,
+
+        /// The name of the inner package
+        #[structopt(name = "INNER")]
+        inner: Name,
+
+        /// Only remove the inner package for this installer
+        #[structopt(long, short)]
+        installer: Option<Name>,
+
+        /// Delete the inner package immediately, before it is removed
+        #[structopt(long)]
+        now: bool,
+    },
+    /// Pull package changes from the package repository's upstream, and apply them
+    Sync {
+        /// Only show the changes, do not pull or apply them
+        #[structopt(long)]
+        plan: bool,
+    },
+    /// Commit local changes to the package repository
+    Commit {
+        /// The commit message, by default one listing the packages changed
+        #[structopt(long, short)]
+        message: Option<String>,
+    },
 }
 
 impl Display for Scope {
@@ -137,8 +266,18 @@ impl FromStr for Scope {
     }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() {
     let args = CommandLine::from_args();
+    let err_styler = Styler::new(args.color.for_stderr());
+    if let Err(err) = run(args) {
+        eprintln!("{} {}", err_styler.error("error:"), err);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: CommandLine) -> Result<(), Box<dyn Error>> {
+    let styler = Styler::new(args.color.for_stdout());
+    let err_styler = Styler::new(args.color.for_stderr());
 
     pretty_env_logger::formatted_builder()
         .filter_level(match args.verbose {
@@ -151,11 +290,22 @@ fn main() -> Result<(), Box<dyn Error>> {
         })
         .init();
 
-    let loaded_config = match current_configuration() {
+    let mut builder = match &args.config_dir {
+        Some(config_dir) => ConfigurationBuilder::new().from_directory(config_dir),
+        None => ConfigurationBuilder::new().with_user_config(),
+    };
+    for packages in &args.packages {
+        builder = builder.package_dir(packages);
+    }
+
+    let loaded_config = match builder.build() {
         Ok(config) => config,
         Err(kind) => {
-            let err: dfpm::error::Error = kind.to_string().into();
-            return Err(Box::new(err));
+            eprintln!("{} {}", err_styler.error("error:"), kind);
+            if let ErrorKind::InvalidConfigFile(file, Some(location), _) = &kind {
+                write_code_frame(&mut std::io::stderr(), file, location)?;
+            }
+            std::process::exit(1);
         }
     };
 
@@ -164,146 +314,343 @@ fn main() -> Result<(), Box<dyn Error>> {
     };
 
     match args.cmd {
-        SubCommand::Config => {
-            let mut root: StringTreeNode = "<config>".into();
-
-            let mut platform_root: StringTreeNode = "<platform>".into();
-            platform_root.push(format!(
-                "{} ({})",
-                loaded_config.platform().name,
-                dfpm::config::platforms::current_name().unwrap()
-            ));
-            root.push_node(platform_root);
-
-            let mut layer_root: StringTreeNode = "<layers>".into();
-            for layer in loaded_config.layers() {
-                let node: StringTreeNode = match layer.as_ref() {
-                    LayerKind::Outer { name, sub_layers } => {
-                        let mut node: StringTreeNode = name.to_string().into();
-                        for layer in sub_layers {
-                            node.push(layer.name().to_string().into())
-                        }
-                        node
-                    }
-                    LayerKind::Inner { name } => name.to_string().into(),
-                };
-                layer_root.push_node(node);
+        SubCommand::Config { cmd } => match cmd {
+            None if args.output != OutputFormat::Text => {
+                print_report(&ConfigReport::new(&loaded_config), args.output)?;
             }
-            root.push_node(layer_root);
-
-            let platform_installer = &loaded_config.platform().system_installer.as_ref().unwrap();
-            let mut installer_root: StringTreeNode = "<installers>".into();
-            for (id, installer) in loaded_config.installers() {
-                installer_root.push(format!(
-                    "{} ({}{})",
-                    installer.name,
-                    if &id == platform_installer { "*" } else { "" },
-                    id,
+            None => {
+                let mut root: StringTreeNode = "<config>".into();
+
+                let mut platform_root: StringTreeNode = "<platform>".into();
+                platform_root.push(format!(
+                    "{} ({})",
+                    loaded_config.platform().name,
+                    loaded_config.platform().target_os
                 ));
-            }
-            root.push_node(installer_root);
+                root.push_node(platform_root);
 
-            root.write(&mut std::io::stdout())?;
-        }
-        SubCommand::Install { scope, names } => match scope {
-            Scope::Package => {
-                info!("SubCommand::Install >> Scope::Package {:?}", names);
-                for name in names {
-                    let package = Package::load(&name)?;
-                    package.install(&install_options)?;
+                let mut layer_root: StringTreeNode = "<layers>".into();
+                for layer in loaded_config.layers() {
+                    layer_root.push_node(layer_tree(layer));
                 }
+                root.push_node(layer_root);
+
+                let platform_installer = loaded_config.platform().system_installer.as_ref();
+                let mut installer_root: StringTreeNode = "<installers>".into();
+                for (id, installer) in loaded_config.installers() {
+                    installer_root.push(format!(
+                        "{} ({}{})",
+                        styler.installer(&installer.name.to_string()),
+                        if Some(id) == platform_installer {
+                            "*"
+                        } else {
+                            ""
+                        },
+                        id,
+                    ));
+                }
+                root.push_node(installer_root);
+
+                root.write(&mut std::io::stdout())?;
             }
-            Scope::Layer => {
-                info!("SubCommand::Install >> Scope::Layer {:?}", names);
-                for layer in loaded_config.layers() {
-                    match layer.as_ref() {
-                        LayerKind::Outer {
-                            name: _,
-                            sub_layers,
-                        } => {
-                            debug!(
-                                "{:?} ∩ {:?}?",
-                                names,
-                                sub_layers
-                                    .iter()
-                                    .map(|layer| layer.name())
-                                    .collect::<Vec<&Name>>(),
-                            );
-                            for layer in sub_layers {
-                                if names.contains(&layer.name()) {
-                                    layer.install(&install_options)?;
-                                }
-                            }
-                        }
-                        LayerKind::Inner { name } => {
-                            debug!("{:?} ∋ {:?}?", name, names);
-                            if names.contains(&name) {
-                                layer.install(&install_options)?;
-                            }
-                        }
+            Some(ConfigCommand::Show { kind, format }) => {
+                let format = format.unwrap_or(args.output);
+                println!("{}", show_config(&loaded_config, kind, format)?);
+            }
+            Some(ConfigCommand::Get { path }) => {
+                match (get_value(&loaded_config, &path)?, args.output) {
+                    (toml::Value::String(value), OutputFormat::Text) => println!("{}", value),
+                    (value, OutputFormat::Text) | (value, OutputFormat::Toml) => {
+                        println!("{}", value)
+                    }
+                    (value, OutputFormat::Json) => {
+                        println!("{}", serde_json::to_string_pretty(&value)?)
                     }
+                    (value, OutputFormat::Yaml) => print!("{}", serde_yaml::to_string(&value)?),
                 }
             }
-            Scope::All => {
-                info!("SubCommand::Install >> Scope::All");
-                for layer in loaded_config.layers() {
-                    layer.install(&install_options)?;
-                }
+            Some(ConfigCommand::Set { path, value }) => {
+                let config_dir = match args.config_dir.clone().or_else(default_config_dir) {
+                    Some(config_dir) => config_dir,
+                    None => return Err("unable to determine the configuration directory".into()),
+                };
+                let file_path = set_value(&loaded_config, &config_dir, &path, &value)?;
+                println!("set {} in {}", path, file_path.to_string_lossy());
             }
         },
-        SubCommand::Delete { scope, names } => match scope {
-            Scope::Package => {
-                for name in names {
-                    let package = Package::load(&name)?;
-                    package.delete(&install_options)?;
+        SubCommand::Install { selection } => {
+            info!("SubCommand::Install >> {:?}", selection);
+            make_selection(selection, false)?.install(&loaded_config, &install_options)?;
+        }
+        SubCommand::Update { selection } => {
+            info!("SubCommand::Update >> {:?}", selection);
+            make_selection(selection, false)?.update(&loaded_config, &install_options)?;
+        }
+        SubCommand::Delete { selection } => {
+            info!("SubCommand::Delete >> {:?}", selection);
+            make_selection(selection, false)?.delete(&loaded_config, &install_options)?;
+        }
+        SubCommand::Inspect { selection } => {
+            let packages = make_selection(selection, true)?.resolve(&loaded_config)?;
+            if args.output == OutputFormat::Text {
+                for package in packages {
+                    package.inspect(&mut std::io::stdout(), &styler)?;
                 }
+            } else {
+                print_report(&packages.iter().collect::<PackagesReport>(), args.output)?;
             }
-            Scope::Layer => {
-                for layer in loaded_config.layers() {
-                    if names.contains(&layer.name()) {
-                        layer.delete(&install_options)?;
+        }
+        SubCommand::Search { tags, text } => {
+            let packages: Vec<Package> = Selection::all()
+                .with_tags(tags)
+                .resolve(&loaded_config)?
+                .into_iter()
+                .filter(|package| package.matches_text(&text))
+                .collect();
+            if args.output == OutputFormat::Text {
+                for package in packages {
+                    match &package.description {
+                        Some(description) => println!(
+                            "{} ({}): {}",
+                            styler.heading(&package.name.to_string()),
+                            styler.dimmed(&package.layer.to_string()),
+                            description
+                        ),
+                        None => println!(
+                            "{} ({})",
+                            styler.heading(&package.name.to_string()),
+                            styler.dimmed(&package.layer.to_string())
+                        ),
                     }
                 }
+            } else {
+                print_report(&packages.iter().collect::<PackagesReport>(), args.output)?;
             }
-            Scope::All => {
-                for layer in loaded_config.layers() {
-                    layer.delete(&install_options)?;
+        }
+        SubCommand::Validate => {
+            let problems = validate_packages(&loaded_config)?;
+            if args.output == OutputFormat::Text {
+                for problem in &problems {
+                    eprintln!("{}", problem);
                 }
-            }
-        },
-        SubCommand::Update { scope, names } => match scope {
-            Scope::Package => {
-                for name in names {
-                    let package = Package::load(&name)?;
-                    package.update(&install_options)?;
+                if !problems.is_empty() {
+                    eprintln!(
+                        "{}",
+                        err_styler.warning(&format!("{} problem(s) found", problems.len()))
+                    );
                 }
+            } else {
+                print_report(&problems.iter().collect::<ProblemsReport>(), args.output)?;
             }
-            Scope::Layer => {
-                for layer in loaded_config.layers() {
-                    if names.contains(&layer.name()) {
-                        layer.update(&install_options)?;
+            if !problems.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        SubCommand::New {
+            name,
+            layer,
+            installer,
+            with_scripts,
+        } => {
+            let package = create_package(
+                &loaded_config,
+                &name,
+                &layer,
+                installer.as_ref(),
+                with_scripts,
+            )?;
+            println!(
+                "created package {} in {}",
+                styler.heading(&package.name.to_string()),
+                package.path().to_string_lossy()
+            );
+        }
+        SubCommand::Add {
+            package,
+            inner,
+            installer,
+            priority,
+            app,
+            platforms,
+            additional_arguments,
+            version,
+            now,
+        } => {
+            let inner_package = InnerPackage {
+                name: inner.clone(),
+                installer: InstallerChain::try_from(installer)?,
+                priority,
+                is_app: app,
+                additional_arguments,
+                platforms,
+                names: Default::default(),
+                version,
+            };
+            let package = add_inner_package(&loaded_config, &package, &inner_package)?;
+            println!("added {} to {}", inner, package.path().to_string_lossy());
+            if now {
+                inner_selection(&package.name, &inner).install(&loaded_config, &install_options)?;
+            }
+        }
+        SubCommand::Remove {
+            package,
+            inner,
+            installer,
+            now,
+        } => {
+            if now {
+                check_inner_package_removable(
+                    &loaded_config,
+                    &package,
+                    &inner,
+                    installer.as_ref(),
+                )?;
+                let selection = match &installer {
+                    Some(installer) => {
+                        inner_selection(&package, &inner).with_installer(installer.clone())
                     }
-                }
+                    None => inner_selection(&package, &inner),
+                };
+                selection.delete(&loaded_config, &install_options)?;
             }
-            Scope::All => {
-                for layer in loaded_config.layers() {
-                    layer.update(&install_options)?;
+            let package =
+                remove_inner_package(&loaded_config, &package, &inner, installer.as_ref())?;
+            println!(
+                "removed {} from {}",
+                inner,
+                package.path().to_string_lossy()
+            );
+        }
+        SubCommand::Sync { plan } => {
+            let repository = Repository::open(&loaded_config)?;
+            let sync_plan = repository.plan()?;
+            if args.output == OutputFormat::Text {
+                if sync_plan.is_empty() {
+                    println!("no incoming package changes");
                 }
+                for change in &sync_plan.changes {
+                    println!("{}", change);
+                }
+            } else {
+                print_report(&sync_plan, args.output)?;
             }
-        },
-        SubCommand::Inspect { package } => match package {
-            Some(package) => {
-                let package = Package::load(&package)?;
-                package.inspect(&mut std::io::stdout(), true)?;
+            if !plan {
+                repository.apply(&loaded_config, &sync_plan, &install_options)?;
             }
-            None => {
-                let packages = get_packages()?;
-                for (_, package) in packages {
-                    package.inspect(&mut std::io::stdout(), true)?;
-                }
+        }
+        SubCommand::Commit { message } => {
+            let repository = Repository::open(&loaded_config)?;
+            match repository.commit(message.as_deref())? {
+                Some(message) => println!("committed: {}", message),
+                None => println!("nothing to commit"),
             }
-        },
+        }
     }
 
     Ok(())
 }
+
+fn print_report<T: Serialize>(report: &T, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    let rendered = render(report, format)?;
+    if rendered.ends_with('\n') {
+        print!("{}", rendered);
+    } else {
+        println!("{}", rendered);
+    }
+    Ok(())
+}
+
+fn inner_selection(package: &Name, inner: &Name) -> Selection {
+    Selection::new(selection::Scope::Packages(vec![QualifiedName {
+        layer: None,
+        name: package.clone(),
+        inner: Some(inner.clone()),
+    }
+    .into()]))
+}
+
+fn make_selection(args: SelectionArgs, default_all: bool) -> Result<Selection, Box<dyn Error>> {
+    let SelectionArgs {
+        scope,
+        tags,
+        only,
+        names,
+    } = args;
+    let implied_all = default_all || !tags.is_empty() || !only.is_empty();
+    let scope = match (scope, names.is_empty()) {
+        (Some(Scope::All), true) => selection::Scope::All,
+        (Some(Scope::All), false) => {
+            return Err("no names may be given with the scope 'all'".into())
+        }
+        (None, true) if implied_all => selection::Scope::All,
+        (_, true) => {
+            return Err("no packages or layers named, use --scope all to select everything".into())
+        }
+        (Some(Scope::Package), false) => selection::Scope::Packages(names),
+        (Some(Scope::Layer), false) => selection::Scope::Layers(
+            names
+                .iter()
+                .map(|name| match name.as_simple() {
+                    Some(name) => Ok(name.clone()),
+                    None => Err(format!("'{}' is not a layer name", name)),
+                })
+                .collect::<Result<Vec<NamePattern>, String>>()?,
+        ),
+        (None, false) => selection::Scope::Names(names),
+    };
+    Ok(Selection::new(scope).with_tags(tags).only(only))
+}
+
+fn layer_tree(layer: &LayerKind) -> StringTreeNode {
+    let mut node: StringTreeNode = layer.name().to_string().into();
+    for sub_layer in layer.sub_layers() {
+        node.push_node(layer_tree(sub_layer));
+    }
+    node
+}
+
+fn write_code_frame(w: &mut impl Write, file: &Path, location: &Location) -> std::io::Result<()> {
+    let content = match std::fs::read_to_string(file) {
+        Ok(content) => content,
+        Err(_) => return Ok(()),
+    };
+    let lines: Vec<&str> = content.lines().collect();
+    if location.line == 0 || location.line > lines.len() + 1 {
+        return Ok(());
+    }
+    let width = location.line.to_string().len();
+    writeln!(
+        w,
+        "{:>width$}--> {}:{}",
+        "",
+        file.to_string_lossy(),
+        location,
+        width = width
+    )?;
+    writeln!(w, "{:>width$} |", "", width = width)?;
+    if location.line > 1 {
+        writeln!(
+            w,
+            "{:>width$} | {}",
+            location.line - 1,
+            lines[location.line - 2],
+            width = width
+        )?;
+    }
+    writeln!(
+        w,
+        "{:>width$} | {}",
+        location.line,
+        lines.get(location.line - 1).unwrap_or(&""),
+        width = width
+    )?;
+    writeln!(
+        w,
+        "{:>width$} | {:>column$}",
+        "",
+        "^",
+        width = width,
+        column = location.column
+    )?;
+    Ok(())
+}
@@ -1,7 +1,10 @@
+use crate::config::{forget_origins, record_origin, ConfigSource, Origins};
 use crate::error::ErrorKind;
 use crate::name::Name;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
@@ -11,6 +14,10 @@ use std::collections::HashMap;
 pub struct Installer {
     pub name: Name,
     #[serde(default)]
+    pub kind: InstallerKind,
+    #[serde(default)]
+    pub script: Option<PathBuf>,
+    #[serde(default)]
     pub platforms: Vec<Name>,
     #[serde(default)]
     pub bootstrap: Option<String>,
@@ -25,39 +32,342 @@ pub struct Installer {
     #[serde(default)]
     pub update_self_arguments: Vec<String>,
     #[serde(default)]
+    pub check_arguments: Vec<String>,
+    #[serde(default)]
+    pub list_arguments: Vec<String>,
+    #[serde(default)]
+    pub version_format: Option<String>,
+    #[serde(default)]
+    pub default_version: Option<String>,
+    #[serde(default)]
+    pub sudo: bool,
+    #[serde(default)]
     pub requires: Vec<String>,
 }
 
+///
+/// How an installer acts; by running `command` with the configured arguments, or by running
+/// `script` which implements the whole protocol described in the `backend` module.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallerKind {
+    #[default]
+    Command,
+    Script,
+}
+
 pub type Installers = HashMap<Name, Installer>;
 
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The form of an installer in a configuration file; any field present replaces the value in an
+/// installer of the same key from an earlier file, and `remove = true` removes it entirely.
+///
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+struct InstallerEntry {
+    remove: bool,
+    name: Option<Name>,
+    kind: Option<InstallerKind>,
+    script: Option<PathBuf>,
+    platforms: Option<Vec<Name>>,
+    bootstrap: Option<String>,
+    command: Option<String>,
+    install_arguments: Option<Vec<String>>,
+    update_arguments: Option<Vec<String>>,
+    delete_arguments: Option<Vec<String>>,
+    update_self_arguments: Option<Vec<String>>,
+    check_arguments: Option<Vec<String>>,
+    list_arguments: Option<Vec<String>>,
+    version_format: Option<String>,
+    default_version: Option<String>,
+    sudo: Option<bool>,
+    requires: Option<Vec<String>>,
+}
+
 // ------------------------------------------------------------------------------------------------
 // Public Functions
 // ------------------------------------------------------------------------------------------------
 
-pub fn load_config() -> Result<Installers, ErrorKind> {
-    info!("load_config()");
-    let mut installers = load_default_config()?;
-    installers.extend(load_user_config()?);
+pub fn load_config(
+    use_defaults: bool,
+    sources: &[ConfigSource],
+    origins: &mut Origins,
+) -> Result<Installers, ErrorKind> {
+    info!("load_config({}, ..)", use_defaults);
+    let mut installers: Installers = Default::default();
+    for (source, config) in super::load_sources::<HashMap<Name, InstallerEntry>>(
+        super::INSTALLERS,
+        if use_defaults {
+            Some(DEFAULT_INSTALLER_CONFIG)
+        } else {
+            None
+        },
+        sources,
+    )? {
+        for (id, entry) in config {
+            entry.merge_into(&mut installers, id, origins, &source);
+        }
+    }
+    for (id, installer) in &installers {
+        if installer.kind == InstallerKind::Script && installer.script.is_none() {
+            let prefix = format!("{}.{}", super::INSTALLERS, id);
+            return Err(ErrorKind::InvalidConfigFile(
+                origins
+                    .get(&format!("{}.kind", prefix))
+                    .or_else(|| origins.get(&prefix))
+                    .cloned()
+                    .unwrap_or_default(),
+                None,
+                format!("installer '{}' is a script installer with no script", id),
+            ));
+        }
+    }
     debug!(".. loaded {} installer configs", installers.len());
     Ok(installers)
 }
 
+///
+/// Replace `{}` in each of `arguments` with `package_argument`.
+///
+pub(crate) fn substitute(arguments: &[String], package_argument: &str) -> Vec<String> {
+    arguments
+        .iter()
+        .map(|argument| argument.replace("{}", package_argument))
+        .collect()
+}
+
 // ------------------------------------------------------------------------------------------------
-// Private Functions
+// Implementations
 // ------------------------------------------------------------------------------------------------
 
-const DEFAULT_INSTALLER_CONFIG: &str = include_str!("installers.toml");
+impl Installer {
+    /// Returns `true` if this installer may be used on the platform named by `target_os`.
+    pub fn supports(&self, target_os: &Name) -> bool {
+        self.platforms.is_empty() || self.platforms.contains(target_os)
+    }
 
-fn load_default_config() -> Result<Installers, ErrorKind> {
-    info!("> load_default_config()");
-    super::config_from_string(DEFAULT_INSTALLER_CONFIG)
+    ///
+    /// Is this installer usable on this machine; its command, or script, is found and so is
+    /// any command it requires.
+    ///
+    pub fn is_available(&self) -> bool {
+        let found = match self.kind {
+            InstallerKind::Command => {
+                !self.command.is_empty() && which::which(&self.command).is_ok()
+            }
+            InstallerKind::Script => self.script.as_ref().is_some_and(|script| script.is_file()),
+        };
+        found
+            && self
+                .requires
+                .iter()
+                .all(|command| which::which(command).is_ok())
+    }
+
+    ///
+    /// The value substituted for `{}` in this installer's arguments; `name` alone, or if a
+    /// version, or default version, is known it is written using `version_format` where
+    /// `{name}` and `{version}` are replaced.
+    ///
+    pub fn package_argument(&self, name: &str, version: Option<&str>) -> String {
+        match (
+            &self.version_format,
+            version.or(self.default_version.as_deref()),
+        ) {
+            (Some(format), Some(version)) => {
+                format.replace("{name}", name).replace("{version}", version)
+            }
+            _ => name.to_string(),
+        }
+    }
+
+    ///
+    /// The command to run this installer with `arguments`, within `sudo` if the installer acts
+    /// on the whole system and the current user is not already root.
+    ///
+    pub fn command(&self, arguments: &[String]) -> Command {
+        let mut command = if self.sudo && !is_root() {
+            let mut command = Command::new("sudo");
+            let _ = command.arg(&self.command);
+            command
+        } else {
+            Command::new(&self.command)
+        };
+        let _ = command.args(arguments);
+        command
+    }
+
+    ///
+    /// Is the package named by `package_argument` already installed, or `None` if this
+    /// installer has no way to check.
+    ///
+    pub fn is_installed(&self, package_argument: &str) -> Option<bool> {
+        if self.check_arguments.is_empty() {
+            return None;
+        }
+        let arguments = substitute(&self.check_arguments, package_argument);
+        trace!("> is_installed `{} {:?}`", self.command, arguments);
+        Command::new(&self.command)
+            .args(&arguments)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .ok()
+    }
+
+    ///
+    /// The installer's own listing of installed packages, one per line, or `None` if it has no
+    /// way to list them.
+    ///
+    pub fn list_installed(&self) -> std::io::Result<Option<Vec<String>>> {
+        if self.list_arguments.is_empty() {
+            return Ok(None);
+        }
+        let output = Command::new(&self.command)
+            .args(&self.list_arguments)
+            .output()?;
+        Ok(Some(
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(String::from)
+                .collect(),
+        ))
+    }
 }
 
-fn load_user_config() -> Result<Installers, ErrorKind> {
-    info!("> load_user_config()");
-    if let Some(content) = super::config_from_user_file("installers")? {
-        super::config_from_string(&content)
-    } else {
-        Ok(Default::default())
+// ------------------------------------------------------------------------------------------------
+
+impl InstallerEntry {
+    fn merge_into(
+        self,
+        installers: &mut Installers,
+        id: Name,
+        origins: &mut Origins,
+        source: &Path,
+    ) {
+        let prefix = format!("{}.{}", super::INSTALLERS, id);
+        if self.remove {
+            if installers.remove(&id).is_none() {
+                warn!("> no installer {} to remove", id);
+            }
+            forget_origins(origins, &prefix);
+        } else if let Some(installer) = installers.get_mut(&id) {
+            trace!("> merging installer {}", id);
+            self.apply(installer, &prefix, origins, source);
+        } else {
+            let mut installer = Installer {
+                name: id.clone(),
+                kind: Default::default(),
+                script: None,
+                platforms: Default::default(),
+                bootstrap: None,
+                command: Default::default(),
+                install_arguments: Default::default(),
+                update_arguments: Default::default(),
+                delete_arguments: Default::default(),
+                update_self_arguments: Default::default(),
+                check_arguments: Default::default(),
+                list_arguments: Default::default(),
+                version_format: None,
+                default_version: None,
+                sudo: false,
+                requires: Default::default(),
+            };
+            record_origin(origins, prefix.clone(), source);
+            self.apply(&mut installer, &prefix, origins, source);
+            let _ = installers.insert(id, installer);
+        }
     }
+
+    fn apply(self, installer: &mut Installer, prefix: &str, origins: &mut Origins, source: &Path) {
+        let mut record =
+            |field: &str| record_origin(origins, format!("{}.{}", prefix, field), source);
+        if let Some(name) = self.name {
+            installer.name = name;
+            record("name");
+        }
+        if let Some(kind) = self.kind {
+            installer.kind = kind;
+            record("kind");
+        }
+        if let Some(script) = self.script {
+            installer.script = Some(match source.parent() {
+                Some(parent) if script.is_relative() && source.is_file() => parent.join(script),
+                _ => script,
+            });
+            record("script");
+        }
+        if let Some(platforms) = self.platforms {
+            installer.platforms = platforms;
+            record("platforms");
+        }
+        if let Some(bootstrap) = self.bootstrap {
+            installer.bootstrap = Some(bootstrap);
+            record("bootstrap");
+        }
+        if let Some(command) = self.command {
+            installer.command = command;
+            record("command");
+        }
+        if let Some(arguments) = self.install_arguments {
+            installer.install_arguments = arguments;
+            record("install_arguments");
+        }
+        if let Some(arguments) = self.update_arguments {
+            installer.update_arguments = arguments;
+            record("update_arguments");
+        }
+        if let Some(arguments) = self.delete_arguments {
+            installer.delete_arguments = arguments;
+            record("delete_arguments");
+        }
+        if let Some(arguments) = self.update_self_arguments {
+            installer.update_self_arguments = arguments;
+            record("update_self_arguments");
+        }
+        if let Some(arguments) = self.check_arguments {
+            installer.check_arguments = arguments;
+            record("check_arguments");
+        }
+        if let Some(arguments) = self.list_arguments {
+            installer.list_arguments = arguments;
+            record("list_arguments");
+        }
+        if let Some(version_format) = self.version_format {
+            installer.version_format = Some(version_format);
+            record("version_format");
+        }
+        if let Some(default_version) = self.default_version {
+            installer.default_version = Some(default_version);
+            record("default_version");
+        }
+        if let Some(sudo) = self.sudo {
+            installer.sudo = sudo;
+            record("sudo");
+        }
+        if let Some(requires) = self.requires {
+            installer.requires = requires;
+            record("requires");
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+const DEFAULT_INSTALLER_CONFIG: &str = include_str!("installers.toml");
+
+fn is_root() -> bool {
+    Command::new("id")
+        .arg("-u")
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim() == "0")
+        .unwrap_or_default()
 }
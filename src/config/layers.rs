@@ -1,18 +1,48 @@
+use crate::config::{forget_origins, record_origin, ConfigSource, Configuration, Origins};
 use crate::error::ErrorKind;
 use crate::name::Name;
-use crate::package::get_packages;
+use crate::package::{Package, ScriptSet};
+use crate::selection::{Scope, Selection};
 use crate::{Installable, Options};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
 // ------------------------------------------------------------------------------------------------
 
+///
+/// A layer, merged from all configuration files; see `LayerEntry` for how each file changes it.
+///
+/// Layers are acted upon in the order they are defined, except that a layer is always ordered
+/// after any layer named in its `after` list, and a `bootstrap` layer is always first.
+///
+/// The `on_install`, `on_update`, and `on_delete` scripts run once before and after all the
+/// selected packages within the layer, and are relative to the package directory. A layer is
+/// skipped entirely if it lists `platforms` that do not include the current one.
+///
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Layer {
     pub name: Name,
     #[serde(default)]
     pub sub_layers: Vec<Name>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub after: Vec<Name>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bootstrap: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_install: Option<ScriptSet>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_update: Option<ScriptSet>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_delete: Option<ScriptSet>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub platforms: Vec<Name>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -20,40 +50,108 @@ pub struct Layers {
     pub layers: Vec<Layer>,
 }
 
+///
+/// The resolved layer tree; a layer listing sub-layers is `Outer`, a leaf layer, to which
+/// packages may belong, is `Inner`. A sub-layer that is also defined as a layer in its own right
+/// is nested in place, so layers may nest to any depth.
+///
 #[derive(Clone, Debug)]
 pub enum LayerKind {
     Outer {
         name: Name,
-        sub_layers: Vec<Box<LayerKind>>,
+        sub_layers: Vec<LayerKind>,
     },
     Inner {
         name: Name,
     },
 }
 
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// A layer as written in a configuration file. A layer with the same name as one from an earlier
+/// file adds its `sub_layers` and `after` to the existing layer, removes any in
+/// `remove_sub_layers`, or with `remove = true` removes the layer entirely. Any other field
+/// present replaces the value from an earlier file, so an empty list clears it.
+///
+#[derive(Clone, Debug, Deserialize)]
+struct LayerEntry {
+    name: Name,
+    #[serde(default)]
+    remove: bool,
+    #[serde(default)]
+    sub_layers: Vec<Name>,
+    #[serde(default)]
+    remove_sub_layers: Vec<Name>,
+    #[serde(default)]
+    after: Vec<Name>,
+    #[serde(default)]
+    bootstrap: Option<bool>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    on_install: Option<ScriptSet>,
+    #[serde(default)]
+    on_update: Option<ScriptSet>,
+    #[serde(default)]
+    on_delete: Option<ScriptSet>,
+    #[serde(default)]
+    requires: Option<Vec<String>>,
+    #[serde(default)]
+    platforms: Option<Vec<Name>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct LayerEntries {
+    layers: Vec<LayerEntry>,
+}
+
 // ------------------------------------------------------------------------------------------------
 // Public Functions
 // ------------------------------------------------------------------------------------------------
 
-pub fn load_config() -> Result<Vec<Box<LayerKind>>, ErrorKind> {
-    info!("load_config()");
-    let mut layers = load_default_config()?;
-    layers.layers.extend(load_user_config()?.layers);
-    debug!(".. loaded {} layer configs", layers.layers.len());
-    Ok(layers
-        .layers
-        .drain(..)
-        .map(|mut layer| {
-            Box::new(LayerKind::Outer {
-                name: layer.name,
-                sub_layers: layer
-                    .sub_layers
-                    .drain(..)
-                    .map(|name| Box::new(LayerKind::Inner { name }))
-                    .collect(),
-            })
-        })
-        .collect())
+///
+/// Load and merge all layer sources, returning the ordered layer tree along with the merged
+/// layer definitions it was built from.
+///
+pub fn load_config(
+    use_defaults: bool,
+    sources: &[ConfigSource],
+    origins: &mut Origins,
+) -> Result<(Vec<LayerKind>, Vec<Layer>), ErrorKind> {
+    info!("load_config({}, ..)", use_defaults);
+    let mut layers: Vec<Layer> = Default::default();
+    let loaded: Vec<(PathBuf, LayerEntries)> = super::load_sources(
+        super::LAYERS,
+        if use_defaults {
+            Some(DEFAULT_LAYER_CONFIG)
+        } else {
+            None
+        },
+        sources,
+    )?;
+    for (source, config) in loaded {
+        for entry in config.layers {
+            entry.merge_into(&mut layers, origins, &source);
+        }
+    }
+    debug!(".. loaded {} layer configs", layers.len());
+    let by_name: HashMap<&Name, &Layer> = layers.iter().map(|l| (&l.name, l)).collect();
+    let tree = build_layer_tree(&layers)?;
+    let known: Vec<&Name> = tree.iter().flat_map(|layer| layer.all_names()).collect();
+    for layer in &layers {
+        if let Some(name) = layer.after.iter().find(|name| !known.contains(name)) {
+            error!(
+                "layer {} is ordered after unknown layer {}",
+                layer.name, name
+            );
+            return Err(ErrorKind::InvalidLayerOrder(name.clone()));
+        }
+    }
+    let tree = order_layers(tree, &by_name)?;
+    Ok((tree, layers))
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -61,79 +159,148 @@ pub fn load_config() -> Result<Vec<Box<LayerKind>>, ErrorKind> {
 // ------------------------------------------------------------------------------------------------
 
 impl Installable for LayerKind {
-    fn install(&self, options: &Options) -> crate::error::Result<()> {
+    fn install(&self, config: &Configuration, options: &Options) -> crate::error::Result<()> {
         info!("Layer::install({:?}) for {:?}", options, self.name());
-        let packages = get_packages().unwrap();
-        let names: Vec<&Name> = match self {
-            Self::Outer {
-                name: _,
-                sub_layers,
-            } => sub_layers
-                .iter()
-                .filter_map(|l| {
-                    if let Self::Inner { name } = l.as_ref() {
-                        Some(name)
-                    } else {
-                        None
-                    }
-                })
-                .collect(),
-            Self::Inner { name } => vec![name],
-        };
-        for name in names {
-            for package in packages.iter().map(|(_, p)| p).filter(|p| &p.layer == name) {
-                package.install(options)?;
-            }
-        }
-        Ok(())
+        Selection::new(Scope::Layers(vec![self.name().clone().into()])).install(config, options)
     }
 
-    fn update(&self, options: &Options) -> crate::error::Result<()> {
+    fn update(&self, config: &Configuration, options: &Options) -> crate::error::Result<()> {
         info!("Layer::update({:?} for {:?})", options, self.name());
-        let packages = get_packages().unwrap();
-        for (_, package) in packages {
-            if &package.layer == self.name() {
-                package.update(options)?;
+        Selection::new(Scope::Layers(vec![self.name().clone().into()])).update(config, options)
+    }
+
+    fn delete(&self, config: &Configuration, options: &Options) -> crate::error::Result<()> {
+        info!("Layer::delete({:?} for {:?})", options, self.name());
+        Selection::new(Scope::Layers(vec![self.name().clone().into()])).delete(config, options)
+    }
+}
+
+impl Layer {
+    /// Returns `true` if this layer applies to the platform named by `target_os`.
+    pub fn supports(&self, target_os: &Name) -> bool {
+        self.platforms.is_empty() || self.platforms.contains(target_os)
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl LayerEntry {
+    fn merge_into(self, layers: &mut Vec<Layer>, origins: &mut Origins, source: &Path) {
+        let prefix = format!("{}.{}", super::LAYERS, self.name);
+        match layers.iter().position(|l| l.name == self.name) {
+            Some(index) if self.remove => {
+                let _ = layers.remove(index);
+                let _ = origins.remove(&prefix);
+                forget_origins(origins, &prefix);
+            }
+            Some(index) => {
+                trace!("> merging layer {}", self.name);
+                self.apply(&mut layers[index], &prefix, origins, source);
+            }
+            None if self.remove => {
+                warn!("> no layer {} to remove", self.name);
+            }
+            None => {
+                let mut layer = Layer {
+                    name: self.name.clone(),
+                    sub_layers: Default::default(),
+                    after: Default::default(),
+                    bootstrap: false,
+                    description: None,
+                    on_install: None,
+                    on_update: None,
+                    on_delete: None,
+                    requires: Default::default(),
+                    platforms: Default::default(),
+                };
+                record_origin(origins, prefix.clone(), source);
+                self.apply(&mut layer, &prefix, origins, source);
+                layers.push(layer);
             }
         }
-        Ok(())
     }
 
-    fn delete(&self, options: &Options) -> crate::error::Result<()> {
-        info!("Layer::delete({:?} for {:?})", options, self.name());
-        let packages = get_packages().unwrap();
-        for (_, package) in packages {
-            if &package.layer == self.name() {
-                package.delete(options)?;
+    fn apply(self, existing: &mut Layer, prefix: &str, origins: &mut Origins, source: &Path) {
+        let before = existing.sub_layers.clone();
+        let before_after = existing.after.clone();
+        for name in self.after {
+            if !existing.after.contains(&name) {
+                existing.after.push(name);
             }
         }
-        Ok(())
+        if existing.after != before_after {
+            record_origin(origins, format!("{}.after", prefix), source);
+        }
+        for sub_layer in self.sub_layers {
+            if !existing.sub_layers.contains(&sub_layer) {
+                existing.sub_layers.push(sub_layer);
+            }
+        }
+        let removed = self.remove_sub_layers;
+        existing
+            .sub_layers
+            .retain(|sub_layer| !removed.contains(sub_layer));
+        if existing.sub_layers != before {
+            record_origin(origins, format!("{}.sub_layers", prefix), source);
+        }
+        let mut record =
+            |field: &str| record_origin(origins, format!("{}.{}", prefix, field), source);
+        if let Some(bootstrap) = self.bootstrap {
+            existing.bootstrap = bootstrap;
+            record("bootstrap");
+        }
+        if self.description.is_some() {
+            existing.description = self.description;
+            record("description");
+        }
+        if self.on_install.is_some() {
+            existing.on_install = self.on_install;
+            record("on_install");
+        }
+        if self.on_update.is_some() {
+            existing.on_update = self.on_update;
+            record("on_update");
+        }
+        if self.on_delete.is_some() {
+            existing.on_delete = self.on_delete;
+            record("on_delete");
+        }
+        if let Some(requires) = self.requires {
+            existing.requires = requires;
+            record("requires");
+        }
+        if let Some(platforms) = self.platforms {
+            existing.platforms = platforms;
+            record("platforms");
+        }
     }
 }
 
+// ------------------------------------------------------------------------------------------------
+
 impl LayerKind {
     pub fn name(&self) -> &Name {
         match self {
             Self::Outer {
                 name,
                 sub_layers: _,
-            } => &name,
-            Self::Inner { name } => &name,
+            } => name,
+            Self::Inner { name } => name,
         }
     }
 
-    pub fn find(&self, a_name: &Name) -> Option<Box<&Self>> {
+    pub fn find(&self, a_name: &Name) -> Option<&Self> {
         match self {
-            Self::Outer {
-                name: _,
-                sub_layers,
-            } => sub_layers
-                .iter()
-                .filter_map(|layer| layer.find(a_name))
-                .next(),
+            Self::Outer { name, sub_layers } => {
+                if a_name == name {
+                    Some(self)
+                } else {
+                    sub_layers.iter().find_map(|layer| layer.find(a_name))
+                }
+            }
             Self::Inner { name } => {
                 if a_name == name {
-                    Some(Box::new(self))
+                    Some(self)
                 } else {
                     None
                 }
@@ -141,21 +308,54 @@ impl LayerKind {
         }
     }
 
-    pub fn is_outer(&self) -> bool {
+    /// The direct sub-layers of this layer, empty for an inner layer.
+    pub fn sub_layers(&self) -> &[LayerKind] {
         match self {
             Self::Outer {
                 name: _,
-                sub_layers: _,
-            } => true,
-            _ => false,
+                sub_layers,
+            } => sub_layers,
+            Self::Inner { name: _ } => &[],
         }
     }
-    pub fn is_inner(&self) -> bool {
+
+    /// The names of all inner layers at any depth below this one, or this layer if it is inner.
+    pub fn leaves(&self) -> Vec<&Name> {
         match self {
-            Self::Inner { name: _ } => true,
-            _ => false,
+            Self::Outer {
+                name: _,
+                sub_layers,
+            } => sub_layers.iter().flat_map(|layer| layer.leaves()).collect(),
+            Self::Inner { name } => vec![name],
         }
     }
+
+    /// All packages that belong to any inner layer within this one, in layer order.
+    pub fn packages(&self, config: &Configuration) -> crate::error::Result<Vec<Package>> {
+        Selection::new(Scope::Layers(vec![self.name().clone().into()])).resolve(config)
+    }
+
+    /// The names of this layer and all layers, at any depth, below it.
+    pub fn all_names(&self) -> Vec<&Name> {
+        let mut names = vec![self.name()];
+        for sub_layer in self.sub_layers() {
+            names.extend(sub_layer.all_names());
+        }
+        names
+    }
+
+    pub fn is_outer(&self) -> bool {
+        matches!(
+            self,
+            Self::Outer {
+                name: _,
+                sub_layers: _,
+            }
+        )
+    }
+    pub fn is_inner(&self) -> bool {
+        matches!(self, Self::Inner { name: _ })
+    }
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -164,18 +364,112 @@ impl LayerKind {
 
 const DEFAULT_LAYER_CONFIG: &str = include_str!("layers.toml");
 
-fn load_default_config() -> Result<Layers, ErrorKind> {
-    info!("> load_default_layer_config()");
-    super::config_from_string(DEFAULT_LAYER_CONFIG)
+fn build_layer_tree(layers: &[Layer]) -> Result<Vec<LayerKind>, ErrorKind> {
+    let mut parents: HashMap<&Name, &Name> = Default::default();
+    for layer in layers {
+        for sub_layer in &layer.sub_layers {
+            if parents.insert(sub_layer, &layer.name).is_some() {
+                error!("layer {} has more than one parent layer", sub_layer);
+                return Err(ErrorKind::InvalidLayerNesting(sub_layer.clone()));
+            }
+        }
+    }
+
+    let by_name: HashMap<&Name, &Layer> = layers.iter().map(|l| (&l.name, l)).collect();
+    let mut visited: Vec<&Name> = Default::default();
+    let tree = layers
+        .iter()
+        .filter(|layer| !parents.contains_key(&layer.name))
+        .map(|layer| build_layer(&layer.name, &by_name, &mut visited))
+        .collect::<Result<Vec<LayerKind>, ErrorKind>>()?;
+
+    match layers.iter().find(|layer| !visited.contains(&&layer.name)) {
+        Some(layer) => {
+            error!("layer {} is nested within itself", layer.name);
+            Err(ErrorKind::InvalidLayerNesting(layer.name.clone()))
+        }
+        None => Ok(tree),
+    }
+}
+
+fn build_layer<'a>(
+    name: &'a Name,
+    by_name: &HashMap<&'a Name, &'a Layer>,
+    visited: &mut Vec<&'a Name>,
+) -> Result<LayerKind, ErrorKind> {
+    if visited.contains(&name) {
+        error!("layer {} is nested within itself", name);
+        return Err(ErrorKind::InvalidLayerNesting(name.clone()));
+    }
+    visited.push(name);
+    match by_name.get(name) {
+        Some(layer) if !layer.sub_layers.is_empty() => Ok(LayerKind::Outer {
+            name: name.clone(),
+            sub_layers: layer
+                .sub_layers
+                .iter()
+                .map(|sub_layer| build_layer(sub_layer, by_name, visited))
+                .collect::<Result<Vec<LayerKind>, ErrorKind>>()?,
+        }),
+        _ => Ok(LayerKind::Inner { name: name.clone() }),
+    }
 }
 
-fn load_user_config() -> Result<Layers, ErrorKind> {
-    info!("> load_user_layer_config()");
-    if let Some(content) = super::config_from_user_file("layers")? {
-        super::config_from_string(&content)
-    } else {
-        Ok(Layers { layers: vec![] })
+///
+/// Order each set of sibling layers, at every depth, so that a layer containing a bootstrap layer
+/// comes first and a layer containing any layer named in an `after` list comes after the layer
+/// containing the named one. Otherwise siblings keep their configured order.
+///
+fn order_layers(
+    layers: Vec<LayerKind>,
+    by_name: &HashMap<&Name, &Layer>,
+) -> Result<Vec<LayerKind>, ErrorKind> {
+    let mut remaining: Vec<LayerKind> = layers
+        .into_iter()
+        .map(|layer| match layer {
+            LayerKind::Outer { name, sub_layers } => Ok(LayerKind::Outer {
+                name,
+                sub_layers: order_layers(sub_layers, by_name)?,
+            }),
+            inner => Ok(inner),
+        })
+        .collect::<Result<Vec<LayerKind>, ErrorKind>>()?;
+
+    let definitions = |layer: &LayerKind| -> Vec<&Layer> {
+        layer
+            .all_names()
+            .iter()
+            .filter_map(|name| by_name.get(name).copied())
+            .collect()
+    };
+    let is_bootstrap = |layer: &LayerKind| definitions(layer).iter().any(|d| d.bootstrap);
+    let must_follow = |layer: &LayerKind, other: &LayerKind| {
+        let other_names = other.all_names();
+        (is_bootstrap(other) && !is_bootstrap(layer))
+            || definitions(layer)
+                .iter()
+                .any(|d| d.after.iter().any(|name| other_names.contains(&name)))
+    };
+
+    let mut ordered: Vec<LayerKind> = Default::default();
+    while !remaining.is_empty() {
+        let next = (0..remaining.len()).find(|index| {
+            let layer = &remaining[*index];
+            !remaining
+                .iter()
+                .enumerate()
+                .any(|(other, o)| other != *index && must_follow(layer, o))
+        });
+        match next {
+            Some(index) => ordered.push(remaining.remove(index)),
+            None => {
+                let name = remaining[0].name().clone();
+                error!("layer {} is ordered after itself", name);
+                return Err(ErrorKind::InvalidLayerOrder(name));
+            }
+        }
     }
+    Ok(ordered)
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -193,6 +487,14 @@ mod tests {
             Layer {
                 name: "bootstrap".parse().unwrap(),
                 sub_layers: vec![],
+                after: vec![],
+                bootstrap: false,
+                description: None,
+                on_install: None,
+                on_update: None,
+                on_delete: None,
+                requires: vec![],
+                platforms: vec![],
             },
             Layer {
                 name: "system".parse().unwrap(),
@@ -203,8 +505,192 @@ mod tests {
                     "secure-tools".parse().unwrap(),
                     "other-tools".parse().unwrap(),
                 ],
+                after: vec![],
+                bootstrap: false,
+                description: None,
+                on_install: None,
+                on_update: None,
+                on_delete: None,
+                requires: vec![],
+                platforms: vec![],
             },
         ];
         println!("{}", toml::to_string(&Layers { layers }).unwrap());
     }
+
+    fn layers_from(content: &str) -> Result<Vec<LayerKind>, ErrorKind> {
+        load_config(
+            false,
+            &[ConfigSource::String(content.to_string())],
+            &mut Default::default(),
+        )
+        .map(|(layers, _)| layers)
+    }
+
+    fn names(layers: &[LayerKind]) -> Vec<String> {
+        layers.iter().map(|l| l.name().to_string()).collect()
+    }
+
+    #[test]
+    fn test_nested_layers() {
+        let layers = layers_from(
+            r#"
+[[layers]]
+name = "bootstrap"
+
+[[layers]]
+name = "development"
+sub_layers = ["tools", "languages"]
+
+[[layers]]
+name = "languages"
+sub_layers = ["jvm", "rust"]
+
+[[layers]]
+name = "jvm"
+sub_layers = ["java", "kotlin"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(layers.len(), 2);
+        assert!(layers[0].is_inner());
+
+        let development = &layers[1];
+        assert!(development.is_outer());
+        let jvm = development.find(&"jvm".parse().unwrap()).unwrap();
+        assert!(jvm.is_outer());
+        assert!(development
+            .find(&"kotlin".parse().unwrap())
+            .unwrap()
+            .is_inner());
+        assert_eq!(
+            development
+                .leaves()
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<String>>(),
+            vec!["tools", "java", "kotlin", "rust"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_nested_layers() {
+        assert!(layers_from(
+            "[[layers]]\nname = \"a\"\nsub_layers = [\"b\"]\n\n[[layers]]\nname = \"b\"\nsub_layers = [\"a\"]\n"
+        )
+        .is_err());
+        assert!(layers_from(
+            "[[layers]]\nname = \"a\"\nsub_layers = [\"c\"]\n\n[[layers]]\nname = \"b\"\nsub_layers = [\"c\"]\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_layer_order() {
+        let layers = layers_from(
+            r#"
+[[layers]]
+name = "development"
+sub_layers = ["tools", "languages"]
+after = ["package-managers"]
+
+[[layers]]
+name = "system"
+sub_layers = ["shell", "package-managers"]
+
+[[layers]]
+name = "bootstrap"
+bootstrap = true
+
+[[layers]]
+name = "languages"
+sub_layers = ["rust", "python"]
+after = ["tools"]
+
+[[layers]]
+name = "tools"
+after = ["rust"]
+"#,
+        );
+        assert!(layers.is_err());
+
+        let layers = layers_from(
+            r#"
+[[layers]]
+name = "development"
+sub_layers = ["languages", "tools"]
+after = ["package-managers"]
+
+[[layers]]
+name = "system"
+sub_layers = ["shell", "package-managers"]
+
+[[layers]]
+name = "bootstrap"
+bootstrap = true
+
+[[layers]]
+name = "languages"
+sub_layers = ["rust", "python"]
+after = ["tools"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(names(&layers), vec!["bootstrap", "system", "development"]);
+        assert_eq!(names(layers[2].sub_layers()), vec!["tools", "languages"]);
+    }
+
+    #[test]
+    fn test_unknown_layer_order() {
+        assert!(layers_from("[[layers]]\nname = \"a\"\nafter = [\"b\"]\n").is_err());
+    }
+
+    #[test]
+    fn test_merge_layer_metadata() {
+        let (_, definitions) = load_config(
+            false,
+            &[
+                ConfigSource::String(
+                    "[[layers]]\nname = \"fonts\"\ndescription = \"Fonts\"\nrequires = [\"fc-cache\"]\n"
+                        .to_string(),
+                ),
+                ConfigSource::String(
+                    "[[layers]]\nname = \"fonts\"\nplatforms = [\"linux\"]\non_install = { after = \"refresh-fonts\" }\n"
+                        .to_string(),
+                ),
+            ],
+            &mut Default::default(),
+        )
+        .unwrap();
+        let fonts = &definitions[0];
+        assert_eq!(fonts.description, Some("Fonts".to_string()));
+        assert_eq!(fonts.requires, vec!["fc-cache".to_string()]);
+        assert_eq!(
+            fonts.on_install.as_ref().and_then(|s| s.after.clone()),
+            Some(PathBuf::from("refresh-fonts"))
+        );
+        assert!(fonts.supports(&"linux".parse().unwrap()));
+        assert!(!fonts.supports(&"macos".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_merge_layer_clears_lists() {
+        let (_, definitions) = load_config(
+            false,
+            &[
+                ConfigSource::String(
+                    "[[layers]]\nname = \"fonts\"\nrequires = [\"fc-cache\"]\nplatforms = [\"linux\"]\n"
+                        .to_string(),
+                ),
+                ConfigSource::String("[[layers]]\nname = \"fonts\"\nrequires = []\n".to_string()),
+                ConfigSource::String("[[layers]]\nname = \"fonts\"\nplatforms = []\n".to_string()),
+            ],
+            &mut Default::default(),
+        )
+        .unwrap();
+        let fonts = &definitions[0];
+        assert!(fonts.requires.is_empty());
+        assert!(fonts.platforms.is_empty());
+        assert!(fonts.supports(&"macos".parse().unwrap()));
+    }
 }
@@ -7,10 +7,14 @@ More detailed description, with
 
 */
 
-use crate::error::ErrorKind;
+use crate::backend::InstallerBackend;
+use crate::error::{describe_toml_error, ErrorKind, Location};
 use crate::name::Name;
-use serde::Deserialize;
-use std::path::PathBuf;
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
@@ -19,133 +23,515 @@ use std::path::PathBuf;
 #[derive(Clone, Debug)]
 pub struct Configuration {
     platform: CurrentPlatform,
-    layers: Vec<Box<LayerKind>>,
+    hostname: Option<String>,
+    platforms: Platforms,
+    layers: Vec<LayerKind>,
+    layer_definitions: Vec<Layer>,
     installers: Installers,
+    package_dirs: Vec<PathBuf>,
+    state_file: Option<PathBuf>,
+    backends: Backends,
+    origins: Origins,
+}
+
+///
+/// The file each configuration value was last set by, keyed by a dotted path such as
+/// `installers.homebrew.command` or `layers.system.sub_layers`.
+///
+pub type Origins = BTreeMap<String, PathBuf>;
+
+/// Installer backends provided in code, keyed by the installer they replace the default for.
+pub type Backends = HashMap<Name, Arc<dyn InstallerBackend>>;
+
+///
+/// Where a single configuration file's content comes from; sources are applied, in order, over
+/// the built-in defaults.
+///
+#[derive(Clone, Debug)]
+pub enum ConfigSource {
+    /// Configuration content held in memory.
+    String(String),
+    /// A file that must exist.
+    File(PathBuf),
+    /// A file that is ignored if it does not exist.
+    OptionalFile(PathBuf),
+}
+
+///
+/// Builds a `Configuration` from the built-in defaults plus any number of strings, files, or
+/// directories, so that library users and tests need not rely on the user's own configuration.
+///
+#[derive(Clone, Debug)]
+pub struct ConfigurationBuilder {
+    use_defaults: bool,
+    target_os: Option<Name>,
+    hostname: Option<String>,
+    package_dirs: Vec<PathBuf>,
+    state_file: Option<PathBuf>,
+    backends: Backends,
+    layers: Vec<ConfigSource>,
+    installers: Vec<ConfigSource>,
+    platforms: Vec<ConfigSource>,
 }
 
 type ConfigResult = Result<Configuration, ErrorKind>;
 
+/// The environment variable naming the directory to load user configuration files from.
+pub const PACKPACK_CONFIG: &str = "PACKPACK_CONFIG";
+
 // ------------------------------------------------------------------------------------------------
 // Public Functions
 // ------------------------------------------------------------------------------------------------
 
 lazy_static! {
-    static ref CURRENT_CONFIG: ConfigResult = load_config();
+    static ref CURRENT_CONFIG: ConfigResult =
+        ConfigurationBuilder::new().with_user_config().build();
 }
 
+///
+/// The configuration built from the defaults and the user's own configuration files, loaded once
+/// on first use. Use `ConfigurationBuilder` for any other configuration, or to reload.
+///
 pub fn current_configuration() -> &'static ConfigResult {
     info!("current_configuration()");
     &CURRENT_CONFIG
 }
 
+///
+/// The directory user configuration files are loaded from; this is the value of
+/// `PACKPACK_CONFIG` if set, else `$XDG_CONFIG_HOME/packpack` if that is set to an absolute
+/// path, else `~/.config/packpack`. This is the same on every platform, rather than macOS using
+/// `~/Library/Application Support`.
+///
+pub fn default_config_dir() -> Option<PathBuf> {
+    match std::env::var(PACKPACK_CONFIG) {
+        Ok(path) if !path.is_empty() => Some(PathBuf::from(path)),
+        _ => xdg_config_dir(std::env::var_os(XDG_CONFIG_HOME), dirs::home_dir())
+            .map(|path| path.join("packpack")),
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // Implementations
 // ------------------------------------------------------------------------------------------------
 
 impl Configuration {
+    pub fn builder() -> ConfigurationBuilder {
+        ConfigurationBuilder::new()
+    }
+
     pub fn platform(&self) -> &CurrentPlatform {
         &self.platform
     }
 
-    pub fn layers(&self) -> impl Iterator<Item = &Box<LayerKind>> {
+    /// The short, lower case, name of the machine packages are installed on, if known.
+    pub fn hostname(&self) -> Option<&str> {
+        self.hostname.as_deref()
+    }
+
+    /// All configured platforms, not only the current one.
+    pub fn platforms(&self) -> &Platforms {
+        &self.platforms
+    }
+
+    pub fn layers(&self) -> impl Iterator<Item = &LayerKind> {
         self.layers.iter()
     }
 
+    pub fn find_layer(&self, name: &Name) -> Option<&LayerKind> {
+        self.layers.iter().find_map(|layer| layer.find(name))
+    }
+
+    /// The merged definition of the named layer, if it was defined rather than only named as a
+    /// sub-layer.
+    pub fn layer_definition(&self, name: &Name) -> Option<&Layer> {
+        self.layer_definitions
+            .iter()
+            .find(|layer| &layer.name == name)
+    }
+
+    /// Installers supported on the current platform.
     pub fn installers(&self) -> impl Iterator<Item = (&Name, &Installer)> {
+        self.installers
+            .iter()
+            .filter(move |(_, i)| i.supports(&self.platform.target_os))
+    }
+
+    /// All configured installers, regardless of platform support.
+    pub fn all_installers(&self) -> impl Iterator<Item = (&Name, &Installer)> {
         self.installers.iter()
     }
+
+    pub fn installer(&self, name: &Name) -> Option<&Installer> {
+        self.installers.get(name)
+    }
+
+    pub fn origins(&self) -> &Origins {
+        &self.origins
+    }
+
+    ///
+    /// The file that set the value at `path`, or the nearest enclosing value; for example
+    /// `installers.homebrew.command`.
+    ///
+    pub fn origin(&self, path: &str) -> Option<&Path> {
+        let mut path = path;
+        loop {
+            if let Some(origin) = self.origins.get(path) {
+                return Some(origin);
+            }
+            match path.rfind('.') {
+                Some(index) => path = &path[..index],
+                None => return None,
+            }
+        }
+    }
+
+    /// The package root with the highest precedence, if any could be determined.
+    pub fn package_dir(&self) -> Option<&Path> {
+        self.package_dirs.last().map(|path| path.as_path())
+    }
+
+    ///
+    /// All the root directories containing package directories, in order of increasing
+    /// precedence; a package in a later root overrides, or extends, one of the same name in an
+    /// earlier root.
+    ///
+    pub fn package_dirs(&self) -> &[PathBuf] {
+        &self.package_dirs
+    }
+
+    /// The file recording what has been installed on this machine, and with which installer.
+    pub fn state_file(&self) -> Option<&Path> {
+        self.state_file.as_deref()
+    }
+
+    /// The installer backends added with `ConfigurationBuilder::backend`.
+    pub fn backends(&self) -> &Backends {
+        &self.backends
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Default for ConfigurationBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigurationBuilder {
+    /// A builder that will only use the built-in defaults.
+    pub fn new() -> Self {
+        Self {
+            use_defaults: true,
+            target_os: None,
+            hostname: None,
+            package_dirs: Default::default(),
+            state_file: None,
+            backends: Default::default(),
+            layers: Default::default(),
+            installers: Default::default(),
+            platforms: Default::default(),
+        }
+    }
+
+    /// Do not include the built-in defaults; only the added sources are used.
+    pub fn without_defaults(mut self) -> Self {
+        self.use_defaults = false;
+        self
+    }
+
+    ///
+    /// Add `path` as a package root, with a higher precedence than any added before it. If none
+    /// are added the defaults from `package::default_package_dirs` are used.
+    ///
+    pub fn package_dir(mut self, path: &Path) -> Self {
+        self.package_dirs.push(path.to_path_buf());
+        self
+    }
+
+    ///
+    /// Use `path` as the state ledger rather than the default from `state::default_state_file`.
+    ///
+    pub fn state_file(mut self, path: &Path) -> Self {
+        self.state_file = Some(path.to_path_buf());
+        self
+    }
+
+    ///
+    /// Use `backend` for the installer `name` rather than the default for its configuration;
+    /// the installer must still be configured, as that is where its platforms are described.
+    ///
+    pub fn backend(mut self, name: Name, backend: Arc<dyn InstallerBackend>) -> Self {
+        let _ = self.backends.insert(name, backend);
+        self
+    }
+
+    /// Select the platform configuration for the named `target_os` rather than the running one.
+    pub fn target_os(mut self, target_os: Name) -> Self {
+        self.target_os = Some(target_os);
+        self
+    }
+
+    /// Use `hostname` for host-specific package overlays rather than the running machine's name.
+    pub fn hostname(mut self, hostname: &str) -> Self {
+        self.hostname = Some(hostname.to_lowercase());
+        self
+    }
+
+    pub fn layers_from_str(mut self, content: &str) -> Self {
+        self.layers.push(ConfigSource::String(content.to_string()));
+        self
+    }
+
+    pub fn layers_from_file(mut self, path: &Path) -> Self {
+        self.layers.push(ConfigSource::File(path.to_path_buf()));
+        self
+    }
+
+    pub fn installers_from_str(mut self, content: &str) -> Self {
+        self.installers
+            .push(ConfigSource::String(content.to_string()));
+        self
+    }
+
+    pub fn installers_from_file(mut self, path: &Path) -> Self {
+        self.installers.push(ConfigSource::File(path.to_path_buf()));
+        self
+    }
+
+    pub fn platforms_from_str(mut self, content: &str) -> Self {
+        self.platforms
+            .push(ConfigSource::String(content.to_string()));
+        self
+    }
+
+    pub fn platforms_from_file(mut self, path: &Path) -> Self {
+        self.platforms.push(ConfigSource::File(path.to_path_buf()));
+        self
+    }
+
+    /// Add any of `layers.toml`, `installers.toml`, and `platforms.toml` found in `path`.
+    pub fn from_directory(mut self, path: &Path) -> Self {
+        self.layers
+            .push(ConfigSource::OptionalFile(make_file_path(path, LAYERS)));
+        self.installers
+            .push(ConfigSource::OptionalFile(make_file_path(path, INSTALLERS)));
+        self.platforms
+            .push(ConfigSource::OptionalFile(make_file_path(path, PLATFORMS)));
+        self
+    }
+
+    /// Add the configuration files in the user's configuration directory, see `default_config_dir`.
+    pub fn with_user_config(self) -> Self {
+        match default_config_dir() {
+            Some(path) => self.from_directory(&path),
+            None => {
+                warn!("> unable to determine the user configuration directory");
+                self
+            }
+        }
+    }
+
+    pub fn build(&self) -> ConfigResult {
+        info!("ConfigurationBuilder::build()");
+
+        let mut origins: Origins = Default::default();
+
+        let (layers, layer_definitions) =
+            layers::load_config(self.use_defaults, &self.layers, &mut origins)?;
+
+        let platforms = platforms::load_config(self.use_defaults, &self.platforms, &mut origins)?;
+        let target_os = match &self.target_os {
+            Some(target_os) => Some(target_os.clone()),
+            None => platforms::current_name(),
+        };
+        let platform = platforms::get_current_platform_config(platforms.clone(), target_os)?;
+        let hostname = match &self.hostname {
+            Some(hostname) => Some(hostname.clone()),
+            None => platforms::current_hostname(),
+        };
+
+        let installers =
+            installers::load_config(self.use_defaults, &self.installers, &mut origins)?;
+
+        let supported = |name: &Name| {
+            installers
+                .get(name)
+                .map(|i| i.supports(&platform.target_os))
+                .unwrap_or(false)
+        };
+
+        trace!("> ensure the selected platform config points to a valid system installer");
+        if let Some(name) = &platform.system_installer {
+            if !supported(name) {
+                error!(
+                    "platform {} could not find system installer for {:?}",
+                    &platform.name,
+                    installers.keys()
+                );
+                return Err(ErrorKind::MissingSystemInstaller);
+            }
+        }
+
+        trace!("> ensure the selected platform config points to a valid application installer");
+        if let Some(name) = &platform.app_installer {
+            if !supported(name) {
+                error!(
+                    "platform {} could not find application installer for {:?}",
+                    &platform.name,
+                    installers.keys()
+                );
+                return Err(ErrorKind::MissingSystemInstaller);
+            }
+        }
+
+        let package_dirs = if self.package_dirs.is_empty() {
+            crate::package::default_package_dirs()
+        } else {
+            self.package_dirs.clone()
+        };
+        debug!("> package directories {:?}", package_dirs);
+
+        Ok(Configuration {
+            layers,
+            layer_definitions,
+            platform,
+            hostname,
+            platforms,
+            installers,
+            package_dirs,
+            state_file: match &self.state_file {
+                Some(state_file) => Some(state_file.clone()),
+                None => crate::state::default_state_file(),
+            },
+            backends: self.backends.clone(),
+            origins,
+        })
+    }
 }
 
 // ------------------------------------------------------------------------------------------------
 // Private Functions
 // ------------------------------------------------------------------------------------------------
 
-fn load_config() -> ConfigResult {
-    info!("load_config()");
+const BUILT_IN: &str = "<built-in>";
 
-    let layers = layers::load_config()?;
+const LAYERS: &str = "layers";
 
-    let platform = platforms::get_current_platform_config()?;
+const INSTALLERS: &str = "installers";
 
-    let mut installers = installers::load_config()?;
-    trace!("> remove installers that are not supported on the current platform");
-    installers.retain(|_, i| i.platforms.is_empty() || i.platforms.contains(&platform.target_os));
+const PLATFORMS: &str = "platforms";
 
-    trace!("> ensure the selected platform config points to a valid system installer");
-    if let Some(name) = &platform.system_installer {
-        if !installers.contains_key(name) {
-            error!(
-                "platform {} could not find system installer for {:?}",
-                &platform.name,
-                installers.keys()
-            );
-            return Err(ErrorKind::MissingSystemInstaller);
-        }
+const XDG_CONFIG_HOME: &str = "XDG_CONFIG_HOME";
+
+///
+/// The XDG base configuration directory; `xdg_config_home` if it is an absolute path, the
+/// specification says any other value is ignored, else `.config` in `home`.
+///
+fn xdg_config_dir(xdg_config_home: Option<OsString>, home: Option<PathBuf>) -> Option<PathBuf> {
+    match xdg_config_home.map(PathBuf::from) {
+        Some(path) if path.is_absolute() => Some(path),
+        _ => home.map(|home| home.join(".config")),
     }
+}
 
-    trace!("> ensure the selected platform config points to a valid application installer");
-    if let Some(name) = &platform.app_installer {
-        if !installers.contains_key(name) {
-            error!(
-                "platform {} could not find application installer for {:?}",
-                &platform.name,
-                installers.keys()
-            );
-            return Err(ErrorKind::MissingSystemInstaller);
+fn load_sources<T>(
+    base_name: &str,
+    default_content: Option<&str>,
+    sources: &[ConfigSource],
+) -> Result<Vec<(PathBuf, T)>, ErrorKind>
+where
+    T: DeserializeOwned,
+{
+    trace!("> > load_sources({:?}, .., {:?})", base_name, sources);
+    let mut results: Vec<(PathBuf, T)> = Default::default();
+    if let Some(content) = default_content {
+        let file_path = PathBuf::from(format!("{}/{}.toml", BUILT_IN, base_name));
+        let config = config_from_string(content, &file_path)?;
+        results.push((file_path, config));
+    }
+    for source in sources {
+        match source {
+            ConfigSource::String(content) => {
+                let file_path = PathBuf::from(format!("<string>/{}.toml", base_name));
+                let config = config_from_string(content, &file_path)?;
+                results.push((file_path, config));
+            }
+            ConfigSource::File(file_path) => {
+                let content = config_from_file(file_path)?;
+                results.push((file_path.clone(), config_from_string(&content, file_path)?));
+            }
+            ConfigSource::OptionalFile(file_path) => {
+                if file_path.is_file() {
+                    let content = config_from_file(file_path)?;
+                    results.push((file_path.clone(), config_from_string(&content, file_path)?));
+                } else {
+                    info!("> > .. config file {:?} does not exist", file_path);
+                }
+            }
         }
     }
+    Ok(results)
+}
 
-    Ok(Configuration {
-        layers,
-        platform,
-        installers,
-    })
+fn record_origin(origins: &mut Origins, path: String, source: &Path) {
+    let _ = origins.insert(path, source.to_path_buf());
 }
 
-fn config_from_string<'a, T>(content: &'a str) -> Result<T, ErrorKind>
+fn forget_origins(origins: &mut Origins, prefix: &str) {
+    let prefix = format!("{}.", prefix);
+    origins.retain(|path, _| !path.starts_with(&prefix));
+}
+
+fn config_from_string<T>(content: &str, file: &Path) -> Result<T, ErrorKind>
 where
-    T: Deserialize<'a>,
+    T: DeserializeOwned,
 {
-    trace!("> > config_from_string(...)");
+    trace!("> > config_from_string(.., {:?})", file);
     match toml::from_str(content) {
         Ok(config) => Ok(config),
         Err(err) => {
             error!("Serde error: {:?}", err);
-            Err(ErrorKind::InvalidConfigFormat)
+            let (location, message) = describe_toml_error(&err);
+            Err(ErrorKind::InvalidConfigFile(
+                file.to_path_buf(),
+                location,
+                message,
+            ))
         }
     }
 }
 
-fn config_from_user_file(file_name: &str) -> Result<Option<String>, ErrorKind> {
-    trace!("> > config_from_user_file({:?})", file_name);
+///
+/// The location of the table, or value, named `key` at the top level of `file`, if the file can
+/// be read and contains it.
+///
+fn locate_key(file: &Path, key: &str) -> Option<Location> {
+    let content = std::fs::read_to_string(file).ok()?;
+    let document = toml_edit::ImDocument::parse(content.as_str()).ok()?;
+    let span = document.get(key)?.span()?;
+    Some(Location::from_offset(&content, span.start))
+}
 
-    if let Some(file_path) = make_user_file_path(file_name) {
-        if file_path.exists() && file_path.is_file() {
-            match std::fs::read_to_string(file_path) {
-                Ok(content) => Ok(Some(content)),
-                Err(err) => {
-                    error!("File read error: {:?}", err);
-                    Err(ErrorKind::InvalidConfigFormat)
-                }
-            }
-        } else {
-            info!("> > .. user config file {:?} does not exist", file_path);
-            Ok(None)
+fn config_from_file(file_path: &Path) -> Result<String, ErrorKind> {
+    trace!("> > config_from_file({:?})", file_path);
+    match std::fs::read_to_string(file_path) {
+        Ok(content) => Ok(content),
+        Err(err) => {
+            error!("File read error: {:?}", err);
+            Err(ErrorKind::InvalidConfigFile(
+                file_path.to_path_buf(),
+                None,
+                err.to_string(),
+            ))
         }
-    } else {
-        warn!("> > .. unable to make a file path for user config file");
-        Ok(None)
     }
 }
 
-fn make_user_file_path(base_name: &str) -> Option<PathBuf> {
-    let base_path = dirs::config_dir();
-    if let Some(mut file_path) = base_path {
-        file_path.push(&format!("{}.toml", base_name));
-        Some(file_path)
-    } else {
-        None
-    }
+fn make_file_path(base_path: &Path, base_name: &str) -> PathBuf {
+    base_path.join(format!("{}.toml", base_name))
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -156,11 +542,14 @@ pub mod installers;
 use installers::{Installer, Installers};
 
 pub mod layers;
-pub use layers::LayerKind;
+pub use layers::{Layer, LayerKind};
 
 pub mod platforms;
 pub use platforms::{CurrentPlatform, Platform, Platforms};
 
+pub mod values;
+pub use values::ConfigKind;
+
 // ------------------------------------------------------------------------------------------------
 // Unit Tests
 // ------------------------------------------------------------------------------------------------
@@ -168,9 +557,29 @@ pub use platforms::{CurrentPlatform, Platform, Platforms};
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::layers::Layers;
+    use crate::error::Location;
     #[allow(unused_imports)]
     use pretty_assertions::{assert_eq, assert_ne};
 
+    #[test]
+    fn test_xdg_config_dir() {
+        let home = Some(PathBuf::from("/home/me"));
+        assert_eq!(
+            xdg_config_dir(Some("/xdg".into()), home.clone()),
+            Some(PathBuf::from("/xdg"))
+        );
+        assert_eq!(
+            xdg_config_dir(Some("relative".into()), home.clone()),
+            Some(PathBuf::from("/home/me/.config"))
+        );
+        assert_eq!(
+            xdg_config_dir(None, home),
+            Some(PathBuf::from("/home/me/.config"))
+        );
+        assert_eq!(xdg_config_dir(None, None), None);
+    }
+
     #[test]
     fn test_load_config() {
         let result = current_configuration();
@@ -179,4 +588,123 @@ mod tests {
         let config = result.as_ref().unwrap();
         println!("{:#?}", config);
     }
+
+    #[test]
+    fn test_build_from_strings() {
+        let result = ConfigurationBuilder::new()
+            .target_os("macos".parse().unwrap())
+            .layers_from_str("[[layers]]\nname = \"extra\"\nsub_layers = [\"things\"]\n")
+            .installers_from_str("[pkgin]\nname = \"pkgin\"\ncommand = \"pkgin\"\n")
+            .build();
+        assert!(result.is_ok());
+        let config = result.unwrap();
+        assert_eq!(config.platform().name, "macOS".parse().unwrap());
+        assert!(config.find_layer(&"things".parse().unwrap()).is_some());
+        assert!(config.installer(&"pkgin".parse().unwrap()).is_some());
+        assert!(config.installers().all(|(id, _)| id.to_string() != "apt"));
+    }
+
+    #[test]
+    fn test_build_without_defaults() {
+        let result = ConfigurationBuilder::new()
+            .without_defaults()
+            .target_os("linux".parse().unwrap())
+            .platforms_from_str("[linux]\nname = \"Linux\"\ndetermine_installer = \"x\"\n")
+            .build();
+        assert!(result.is_ok());
+        let config = result.unwrap();
+        assert_eq!(config.layers().count(), 0);
+        assert_eq!(config.all_installers().count(), 0);
+    }
+
+    #[test]
+    fn test_config_error_location() {
+        let result: Result<Layers, ErrorKind> = config_from_string(
+            "[[layers]]\nname = \"bootstrap\"\nsub_layers = 12\n",
+            &PathBuf::from("layers.toml"),
+        );
+        match result {
+            Err(ErrorKind::InvalidConfigFile(file, location, message)) => {
+                assert_eq!(file, PathBuf::from("layers.toml"));
+                assert_eq!(
+                    location,
+                    Some(Location {
+                        line: 3,
+                        column: 14
+                    })
+                );
+                assert!(!message.contains(" at line "));
+            }
+            _ => panic!("expected an InvalidConfigFile error"),
+        }
+    }
+
+    #[test]
+    fn test_merge_user_config() {
+        let result = ConfigurationBuilder::new()
+            .target_os("macos".parse().unwrap())
+            .layers_from_str(
+                "[[layers]]\nname = \"system\"\nsub_layers = [\"editors\"]\nremove_sub_layers = [\"fonts\"]\n\n[[layers]]\nname = \"games\"\nremove = true\n",
+            )
+            .installers_from_str(
+                "[homebrew]\ninstall_arguments = [\"install\", \"--force\", \"{}\"]\n\n[raco]\nremove = true\n",
+            )
+            .platforms_from_str("[macos]\napp_installer = \"homebrew\"\n")
+            .build();
+        assert!(result.is_ok());
+        let config = result.unwrap();
+
+        assert!(config.find_layer(&"editors".parse().unwrap()).is_some());
+        assert!(config.find_layer(&"shell".parse().unwrap()).is_some());
+        assert!(config.find_layer(&"fonts".parse().unwrap()).is_none());
+        assert!(config.find_layer(&"games".parse().unwrap()).is_none());
+
+        let homebrew = config.installer(&"homebrew".parse().unwrap()).unwrap();
+        assert_eq!(homebrew.command, "brew".to_string());
+        assert_eq!(homebrew.install_arguments, vec!["install", "--force", "{}"]);
+        assert!(config.installer(&"raco".parse().unwrap()).is_none());
+
+        assert_eq!(config.platform().name, "macOS".parse().unwrap());
+        assert_eq!(
+            config.platform().app_installer,
+            Some("homebrew".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_builtin_installers() {
+        let config = ConfigurationBuilder::new()
+            .target_os("linux".parse().unwrap())
+            .build()
+            .unwrap();
+        let supported: Vec<String> = config.installers().map(|(id, _)| id.to_string()).collect();
+        for id in &[
+            "apt", "dnf", "pacman", "zypper", "apk", "flatpak", "snap", "pipx", "pip", "npm",
+            "gem", "go",
+        ] {
+            assert!(supported.contains(&id.to_string()), "missing {}", id);
+        }
+        assert!(!supported.contains(&"homebrew".to_string()));
+
+        let installer = |id: &str| config.installer(&id.parse().unwrap()).unwrap().clone();
+        assert_eq!(
+            installer("go").package_argument("gopls", None),
+            "gopls@latest"
+        );
+        assert_eq!(
+            installer("pip").package_argument("black", Some("22.1")),
+            "black==22.1"
+        );
+        assert_eq!(installer("npm").package_argument("yarn", None), "yarn");
+        assert_eq!(
+            installer("flatpak").package_argument("org.gimp.GIMP", Some("2")),
+            "org.gimp.GIMP"
+        );
+        assert!(installer("pacman").sudo);
+        assert!(!installer("pipx").sudo);
+        assert_eq!(
+            installers::substitute(&installer("gem").install_arguments, "rake:13.0"),
+            vec!["install", "--user-install", "rake:13.0"]
+        );
+    }
 }
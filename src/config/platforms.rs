@@ -1,8 +1,9 @@
+use crate::config::{forget_origins, record_origin, ConfigSource, Origins};
 use crate::error::ErrorKind;
 use crate::name::Name;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
@@ -30,6 +31,24 @@ pub struct CurrentPlatform {
 
 pub type Platforms = HashMap<Name, Platform>;
 
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The form of a platform in a configuration file; any field present replaces the value in a
+/// platform of the same key from an earlier file, and `remove = true` removes it entirely.
+///
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PlatformEntry {
+    remove: bool,
+    name: Option<Name>,
+    system_installer: Option<Name>,
+    app_installer: Option<Name>,
+    determine_installer: Option<PathBuf>,
+}
+
 // ------------------------------------------------------------------------------------------------
 // Public Functions
 // ------------------------------------------------------------------------------------------------
@@ -66,10 +85,32 @@ pub fn current_name() -> Option<Name> {
     }
 }
 
-pub fn get_current_platform_config() -> Result<CurrentPlatform, ErrorKind> {
-    info!("get_current_platform_config()");
-    let mut platforms = load_config()?;
-    if let Some(name) = current_name() {
+///
+/// The short name of the machine this is running on, from the `hostname` command or else
+/// `/etc/hostname`, without any domain and in lower case.
+///
+pub fn current_hostname() -> Option<String> {
+    let hostname = match std::process::Command::new("hostname").output() {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).to_string()
+        }
+        _ => std::fs::read_to_string("/etc/hostname").ok()?,
+    };
+    let hostname = hostname.trim().split('.').next().unwrap_or_default();
+    info!("current_hostname() -> {:?}", hostname);
+    if hostname.is_empty() {
+        None
+    } else {
+        Some(hostname.to_lowercase())
+    }
+}
+
+pub fn get_current_platform_config(
+    mut platforms: Platforms,
+    target_os: Option<Name>,
+) -> Result<CurrentPlatform, ErrorKind> {
+    info!("get_current_platform_config(.., {:?})", target_os);
+    if let Some(name) = target_os {
         match platforms.remove(&name) {
             Some(platform) => Ok(CurrentPlatform {
                 target_os: name,
@@ -89,43 +130,112 @@ pub fn get_current_platform_config() -> Result<CurrentPlatform, ErrorKind> {
     }
 }
 
-pub fn load_config() -> Result<Platforms, ErrorKind> {
-    info!("load_config()");
-    let mut platforms = load_default_config()?;
-    platforms.extend(load_user_config()?);
+pub fn load_config(
+    use_defaults: bool,
+    sources: &[ConfigSource],
+    origins: &mut Origins,
+) -> Result<Platforms, ErrorKind> {
+    info!("load_config({}, ..)", use_defaults);
+    let mut platforms: Platforms = Default::default();
+    for (source, config) in super::load_sources::<HashMap<Name, PlatformEntry>>(
+        super::PLATFORMS,
+        if use_defaults {
+            Some(DEFAULT_PLATFORM_CONFIG)
+        } else {
+            None
+        },
+        sources,
+    )? {
+        for (id, entry) in config {
+            entry.merge_into(&mut platforms, id, origins, &source);
+        }
+    }
 
-    if platforms
+    match platforms
         .iter()
-        .all(|(_, p)| p.system_installer.is_some() || p.determine_installer.is_some())
+        .find(|(_, p)| p.system_installer.is_none() && p.determine_installer.is_none())
     {
-        debug!(".. loaded {} platform configs", platforms.len());
-        Ok(platforms)
-    } else {
-        error!("Platforms must have either `system_installer` or `determine_installer` specified");
-        Err(ErrorKind::InvalidConfigFormat)
+        None => {
+            debug!(".. loaded {} platform configs", platforms.len());
+            Ok(platforms)
+        }
+        Some((id, _)) => {
+            error!(
+                "Platforms must have either `system_installer` or `determine_installer` specified"
+            );
+            let file = origins
+                .get(&format!("{}.{}", super::PLATFORMS, id))
+                .cloned()
+                .unwrap_or_default();
+            let location = super::locate_key(&file, &id.to_string());
+            Err(ErrorKind::InvalidConfigFile(
+                file,
+                location,
+                format!(
+                    "platform '{}' has neither a system_installer nor a determine_installer",
+                    id
+                ),
+            ))
+        }
     }
 }
 
 // ------------------------------------------------------------------------------------------------
-// Private Functions
+// Implementations
 // ------------------------------------------------------------------------------------------------
 
-const DEFAULT_PLATFORM_CONFIG: &str = include_str!("platforms.toml");
-
-fn load_default_config() -> Result<Platforms, ErrorKind> {
-    info!("> load_default_platform_config()");
-    super::config_from_string(DEFAULT_PLATFORM_CONFIG)
-}
+impl PlatformEntry {
+    fn merge_into(self, platforms: &mut Platforms, id: Name, origins: &mut Origins, source: &Path) {
+        let prefix = format!("{}.{}", super::PLATFORMS, id);
+        if self.remove {
+            if platforms.remove(&id).is_none() {
+                warn!("> no platform {} to remove", id);
+            }
+            forget_origins(origins, &prefix);
+        } else if let Some(platform) = platforms.get_mut(&id) {
+            trace!("> merging platform {}", id);
+            self.apply(platform, &prefix, origins, source);
+        } else {
+            let mut platform = Platform {
+                name: id.clone(),
+                system_installer: None,
+                app_installer: None,
+                determine_installer: None,
+            };
+            record_origin(origins, prefix.clone(), source);
+            self.apply(&mut platform, &prefix, origins, source);
+            let _ = platforms.insert(id, platform);
+        }
+    }
 
-fn load_user_config() -> Result<Platforms, ErrorKind> {
-    info!("> load_user_platform_config()");
-    if let Some(content) = super::config_from_user_file("platforms")? {
-        super::config_from_string(&content)
-    } else {
-        Ok(Default::default())
+    fn apply(self, platform: &mut Platform, prefix: &str, origins: &mut Origins, source: &Path) {
+        let mut record =
+            |field: &str| record_origin(origins, format!("{}.{}", prefix, field), source);
+        if let Some(name) = self.name {
+            platform.name = name;
+            record("name");
+        }
+        if let Some(installer) = self.system_installer {
+            platform.system_installer = Some(installer);
+            record("system_installer");
+        }
+        if let Some(installer) = self.app_installer {
+            platform.app_installer = Some(installer);
+            record("app_installer");
+        }
+        if let Some(determine_installer) = self.determine_installer {
+            platform.determine_installer = Some(determine_installer);
+            record("determine_installer");
+        }
     }
 }
 
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+const DEFAULT_PLATFORM_CONFIG: &str = include_str!("platforms.toml");
+
 // ------------------------------------------------------------------------------------------------
 // Unit Tests
 // ------------------------------------------------------------------------------------------------
@@ -133,9 +243,27 @@ fn load_user_config() -> Result<Platforms, ErrorKind> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::Location;
+    use crate::testing::temp_dir;
     #[allow(unused_imports)]
     use pretty_assertions::{assert_eq, assert_ne};
 
+    #[test]
+    fn test_platform_without_installer() {
+        let temp = temp_dir();
+        let file = temp.path().join("platforms.toml");
+        std::fs::write(&file, "# mine\n\n[plan9]\nname = \"Plan 9\"\n").unwrap();
+        let mut origins: Origins = Default::default();
+        match load_config(false, &[ConfigSource::File(file.clone())], &mut origins) {
+            Err(ErrorKind::InvalidConfigFile(error_file, location, message)) => {
+                assert_eq!(error_file, file);
+                assert_eq!(location, Some(Location { line: 3, column: 1 }));
+                assert!(message.contains("'plan9'"));
+            }
+            _ => panic!("expected an InvalidConfigFile error"),
+        }
+    }
+
     #[test]
     fn serialize_platforms() {
         println!(
@@ -0,0 +1,448 @@
+use crate::config::layers::{Layer, Layers};
+use crate::config::{ConfigSource, Configuration, Origins, INSTALLERS, LAYERS, PLATFORMS};
+use crate::error::{ErrorKind, Result};
+use crate::OutputFormat;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use toml::value::Table;
+use toml::Value;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The separate sections of the configuration, each of which is loaded from its own file.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigKind {
+    Platform,
+    Layers,
+    Installers,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Render the effective configuration, or one section of it, in the requested format. Text and
+/// TOML output notes the file that set each value in a trailing comment, JSON and YAML output
+/// includes an `origins` object mapping value paths to files.
+///
+pub fn show_config(
+    config: &Configuration,
+    kind: Option<ConfigKind>,
+    format: OutputFormat,
+) -> Result<String> {
+    info!("show_config(.., {:?}, {:?})", kind, format);
+    let value = effective_config(config, kind, false)?;
+    match format {
+        OutputFormat::Text | OutputFormat::Toml => {
+            let content = toml::to_string(&value)?;
+            let mut document: toml_edit::DocumentMut = content
+                .parse()
+                .map_err(|e: toml_edit::TomlError| ErrorKind::Msg(e.to_string()))?;
+            annotate_table(document.as_table_mut(), "", config);
+            Ok(document.to_string())
+        }
+        OutputFormat::Json | OutputFormat::Yaml => {
+            let prefix = kind.map(|k| k.path_prefix(config));
+            let origins: Origins = config
+                .origins()
+                .iter()
+                .filter(|(path, _)| match &prefix {
+                    Some(prefix) => path.starts_with(prefix.as_str()),
+                    None => true,
+                })
+                .map(|(path, file)| (path.clone(), file.clone()))
+                .collect();
+            let mut json = serde_json::to_value(&value)?;
+            if let serde_json::Value::Object(map) = &mut json {
+                let _ = map.insert("origins".to_string(), serde_json::to_value(origins)?);
+            }
+            crate::report::render(&json, format)
+        }
+    }
+}
+
+///
+/// Return the effective value at the dotted `path`, for example `installers.homebrew.command`
+/// or `layers.system.sub_layers`. The prefix `platform` is a shortcut for the current
+/// platform's entry in `platforms`.
+///
+pub fn get_value(config: &Configuration, path: &str) -> Result<Value> {
+    info!("get_value(.., {:?})", path);
+    let path = expand_path(config, path);
+    let root = effective_config(config, None, true)?;
+    let mut current = &root;
+    for segment in path.split('.') {
+        current = match current {
+            Value::Table(table) => table.get(segment),
+            Value::Array(array) => find_in_array(array, segment),
+            _ => None,
+        }
+        .ok_or_else(|| ErrorKind::InvalidConfigPath(path.clone()))?;
+    }
+    Ok(current.clone())
+}
+
+///
+/// Set the value at the dotted `path` in the matching user configuration file in `config_dir`,
+/// creating the file if necessary and preserving existing formatting and comments. The `value`
+/// is parsed as a TOML value, if that fails it is used as a plain string. As for `get_value` a
+/// path starting with `platform` refers to the current platform. Returns the path of the file
+/// written.
+///
+pub fn set_value(
+    config: &Configuration,
+    config_dir: &Path,
+    path: &str,
+    value: &str,
+) -> Result<PathBuf> {
+    info!("set_value(.., {:?}, {:?}, {:?})", config_dir, path, value);
+    let path = expand_path(config, path);
+    let path = path.as_str();
+    let segments: Vec<&str> = path.split('.').collect();
+    if segments.len() < 3 || segments.iter().any(|s| s.is_empty()) {
+        return Err(ErrorKind::InvalidConfigPath(path.to_string()).into());
+    }
+    let base_name = match segments[0] {
+        LAYERS | INSTALLERS | PLATFORMS => segments[0],
+        _ => return Err(ErrorKind::InvalidConfigPath(path.to_string()).into()),
+    };
+    let file_path = super::make_file_path(config_dir, base_name);
+    let content = if file_path.is_file() {
+        std::fs::read_to_string(&file_path)?
+    } else {
+        String::new()
+    };
+    let mut document: toml_edit::DocumentMut =
+        content.parse().map_err(|e: toml_edit::TomlError| {
+            ErrorKind::InvalidConfigFile(file_path.clone(), None, e.to_string())
+        })?;
+
+    let new_value = match value.parse::<toml_edit::Value>() {
+        Ok(value) => value,
+        Err(_) => toml_edit::Value::from(value),
+    };
+
+    let table = if base_name == LAYERS {
+        layer_table(document.as_table_mut(), segments[1])
+            .ok_or_else(|| ErrorKind::InvalidConfigPath(path.to_string()))?
+    } else {
+        document.as_table_mut()
+    };
+    let rest = if base_name == LAYERS {
+        &segments[2..]
+    } else {
+        &segments[1..]
+    };
+    set_in_table(table, rest, new_value)
+        .ok_or_else(|| ErrorKind::InvalidConfigPath(path.to_string()))?;
+
+    let content = document.to_string();
+    check_content(base_name, &content, &file_path)?;
+    if let Some(parent) = file_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&file_path, content)?;
+    Ok(file_path)
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Display for ConfigKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ConfigKind::Platform => "platform",
+                ConfigKind::Layers => "layers",
+                ConfigKind::Installers => "installers",
+            }
+        )
+    }
+}
+
+impl FromStr for ConfigKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "platform" => Ok(ConfigKind::Platform),
+            "layers" => Ok(ConfigKind::Layers),
+            "installers" => Ok(ConfigKind::Installers),
+            _ => Err(format!("invalid value '{}' for config kind", s)),
+        }
+    }
+}
+
+impl ConfigKind {
+    fn path_prefix(&self, config: &Configuration) -> String {
+        match self {
+            ConfigKind::Platform => format!("{}.{}", PLATFORMS, config.platform().target_os),
+            ConfigKind::Layers => LAYERS.to_string(),
+            ConfigKind::Installers => INSTALLERS.to_string(),
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+fn layer_definitions(config: &Configuration) -> Vec<Layer> {
+    config
+        .layers()
+        .flat_map(|layer| layer.all_names())
+        .filter_map(|name| config.layer_definition(name))
+        .cloned()
+        .collect()
+}
+
+fn effective_config(
+    config: &Configuration,
+    kind: Option<ConfigKind>,
+    all_platforms: bool,
+) -> Result<Value> {
+    let mut root = Table::new();
+    if kind.is_none() || kind == Some(ConfigKind::Platform) {
+        let mut platforms = Table::new();
+        for (id, platform) in config.platforms() {
+            if all_platforms || id == &config.platform().target_os {
+                let _ = platforms.insert(id.to_string(), Value::try_from(platform)?);
+            }
+        }
+        let _ = root.insert(PLATFORMS.to_string(), Value::Table(platforms));
+    }
+    if kind.is_none() || kind == Some(ConfigKind::Layers) {
+        let layers = Layers {
+            layers: layer_definitions(config),
+        };
+        if let Value::Table(layers) = Value::try_from(layers)? {
+            root.extend(layers);
+        }
+    }
+    if kind.is_none() || kind == Some(ConfigKind::Installers) {
+        let mut installers = Table::new();
+        for (id, installer) in config.all_installers() {
+            let _ = installers.insert(id.to_string(), Value::try_from(installer)?);
+        }
+        let _ = root.insert(INSTALLERS.to_string(), Value::Table(installers));
+    }
+    Ok(Value::Table(root))
+}
+
+fn expand_path(config: &Configuration, path: &str) -> String {
+    match path.split_once('.') {
+        Some(("platform", rest)) => {
+            format!("{}.{}", ConfigKind::Platform.path_prefix(config), rest)
+        }
+        None if path == "platform" => ConfigKind::Platform.path_prefix(config),
+        _ => path.to_string(),
+    }
+}
+
+fn find_in_array<'a>(array: &'a [Value], segment: &str) -> Option<&'a Value> {
+    match segment.parse::<usize>() {
+        Ok(index) => array.get(index),
+        Err(_) => array.iter().find(|value| {
+            value
+                .get("name")
+                .and_then(|name| name.as_str())
+                .map(|name| name == segment)
+                .unwrap_or(false)
+        }),
+    }
+}
+
+fn annotate_table(table: &mut toml_edit::Table, prefix: &str, config: &Configuration) {
+    for (key, item) in table.iter_mut() {
+        let path = if prefix.is_empty() {
+            key.get().to_string()
+        } else {
+            format!("{}.{}", prefix, key.get())
+        };
+        match item {
+            toml_edit::Item::Value(value) => {
+                if let Some(origin) = config.origin(&path) {
+                    value
+                        .decor_mut()
+                        .set_suffix(format!(" # {}", origin.to_string_lossy()));
+                }
+            }
+            toml_edit::Item::Table(table) => annotate_table(table, &path, config),
+            toml_edit::Item::ArrayOfTables(array) => {
+                for table in array.iter_mut() {
+                    let name = table
+                        .get("name")
+                        .and_then(|name| name.as_str())
+                        .unwrap_or_default()
+                        .to_string();
+                    annotate_table(table, &format!("{}.{}", path, name), config);
+                }
+            }
+            toml_edit::Item::None => {}
+        }
+    }
+}
+
+fn layer_table<'a>(
+    document: &'a mut toml_edit::Table,
+    name: &str,
+) -> Option<&'a mut toml_edit::Table> {
+    let layers = document
+        .entry(LAYERS)
+        .or_insert_with(|| toml_edit::Item::ArrayOfTables(Default::default()))
+        .as_array_of_tables_mut()?;
+    let position = layers
+        .iter()
+        .position(|table| table.get("name").and_then(|n| n.as_str()) == Some(name));
+    let index = match position {
+        Some(index) => index,
+        None => {
+            let mut table = toml_edit::Table::new();
+            let _ = table.insert("name", toml_edit::value(name));
+            layers.push(table);
+            layers.len() - 1
+        }
+    };
+    layers.get_mut(index)
+}
+
+fn set_in_table(
+    table: &mut toml_edit::Table,
+    segments: &[&str],
+    value: toml_edit::Value,
+) -> Option<()> {
+    let (last, parents) = segments.split_last()?;
+    let mut current = table;
+    for segment in parents {
+        let item = current
+            .entry(segment)
+            .or_insert_with(|| toml_edit::Item::Table(toml_edit::Table::new()));
+        current = item.as_table_mut()?;
+    }
+    match current.get_mut(last) {
+        Some(toml_edit::Item::Value(existing)) => {
+            let decor = existing.decor().clone();
+            *existing = value;
+            *existing.decor_mut() = decor;
+        }
+        _ => {
+            let _ = current.insert(last, toml_edit::Item::Value(value));
+        }
+    }
+    Some(())
+}
+
+fn check_content(base_name: &str, content: &str, file_path: &Path) -> Result<()> {
+    let sources = vec![ConfigSource::String(content.to_string())];
+    let mut origins: Origins = Default::default();
+    let result = match base_name {
+        LAYERS => super::layers::load_config(true, &sources, &mut origins).map(|_| ()),
+        INSTALLERS => super::installers::load_config(true, &sources, &mut origins).map(|_| ()),
+        _ => super::platforms::load_config(true, &sources, &mut origins).map(|_| ()),
+    };
+    match result {
+        Ok(()) => Ok(()),
+        Err(ErrorKind::InvalidConfigFile(_, location, message)) => {
+            Err(ErrorKind::InvalidConfigFile(file_path.to_path_buf(), location, message).into())
+        }
+        Err(kind) => Err(kind.into()),
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigurationBuilder;
+    use crate::testing::temp_dir;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[test]
+    fn test_get_value() {
+        let config = ConfigurationBuilder::new()
+            .target_os("macos".parse().unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(
+            get_value(&config, "installers.homebrew.command").unwrap(),
+            Value::String("brew".to_string())
+        );
+        assert_eq!(
+            get_value(&config, "platform.system_installer").unwrap(),
+            Value::String("homebrew".to_string())
+        );
+        assert!(get_value(&config, "layers.system.sub_layers")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .contains(&Value::String("fonts".to_string())));
+        assert!(get_value(&config, "installers.nope").is_err());
+    }
+
+    #[test]
+    fn test_show_config_origins() {
+        let config = ConfigurationBuilder::new()
+            .target_os("macos".parse().unwrap())
+            .installers_from_str("[homebrew]\ncommand = \"brew2\"\n")
+            .build()
+            .unwrap();
+        let shown = show_config(&config, Some(ConfigKind::Installers), OutputFormat::Toml).unwrap();
+        assert!(shown.contains("command = \"brew2\" # <string>/installers.toml"));
+        assert!(shown.contains("name = \"Homebrew\" # <built-in>/installers.toml"));
+    }
+
+    #[test]
+    fn test_set_value_preserves_comments() {
+        let temp = temp_dir();
+        let config_dir = temp.path();
+        let file_path = config_dir.join("installers.toml");
+        std::fs::write(
+            &file_path,
+            "# mine\n[homebrew]\ncommand = \"brew\" # keep\n",
+        )
+        .unwrap();
+        let config = ConfigurationBuilder::new()
+            .target_os("macos".parse().unwrap())
+            .build()
+            .unwrap();
+
+        let result = set_value(&config, config_dir, "installers.homebrew.command", "brew2");
+        assert_eq!(result.unwrap(), file_path);
+        let result = set_value(
+            &config,
+            config_dir,
+            "layers.system.sub_layers",
+            "[\"editors\"]",
+        );
+        assert!(result.is_ok());
+        assert!(set_value(&config, config_dir, "installers.homebrew.command", "12").is_err());
+        let result = set_value(&config, config_dir, "platform.app_installer", "homebrew");
+        assert_eq!(result.unwrap(), config_dir.join("platforms.toml"));
+
+        let content = std::fs::read_to_string(&file_path).unwrap();
+        assert_eq!(content, "# mine\n[homebrew]\ncommand = \"brew2\" # keep\n");
+
+        let config = ConfigurationBuilder::new()
+            .from_directory(config_dir)
+            .build()
+            .unwrap();
+        assert!(config.find_layer(&"editors".parse().unwrap()).is_some());
+        assert_eq!(
+            get_value(&config, "platforms.macos.app_installer").unwrap(),
+            Value::String("homebrew".to_string())
+        );
+    }
+}
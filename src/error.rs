@@ -8,6 +8,9 @@ More detailed description, with
 */
 
 use crate::name::Name;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
@@ -15,47 +18,167 @@ use crate::name::Name;
 
 error_chain! {
     errors {
-        #[doc("Invalid Name, either empty or contains incorrect characters")]
+        #[doc = "Invalid Name, either empty or contains incorrect characters"]
         InvalidName(v: String) {
             description("Invalid Name, either empty or contains incorrect characters")
             display("Invalid Name '{}', either empty or contains incorrect characters", v)
         }
-        #[doc("Could not parse configuration file")]
+        #[doc = "Could not parse configuration file"]
         InvalidConfigFormat {
             description("Could not parse configuration file")
             display("Could not parse configuration file")
         }
-        #[doc("Package `layer` is either invalid, or is an outer layer")]
+        #[doc = "Could not read or parse the named configuration file"]
+        InvalidConfigFile(file: PathBuf, location: Option<Location>, message: String) {
+            description("Could not read or parse the named configuration file")
+            display(
+                "Could not parse configuration file {}{}: {}",
+                file.to_string_lossy(),
+                match location { Some(location) => format!(":{}", location), None => String::new() },
+                message
+            )
+        }
+        #[doc = "No configuration value exists, or may be set, at the given path"]
+        InvalidConfigPath(path: String) {
+            description("No configuration value exists, or may be set, at the given path")
+            display("No configuration value exists, or may be set, at the path '{}'", path)
+        }
+        #[doc = "A layer is nested within itself, or within more than one parent layer"]
+        InvalidLayerNesting(n: Name) {
+            description("A layer is nested within itself, or within more than one parent layer")
+            display("The layer '{}' is nested within itself, or within more than one parent layer", n)
+        }
+        #[doc = "A layer is ordered after itself, or after a layer that does not exist"]
+        InvalidLayerOrder(n: Name) {
+            description("A layer is ordered after itself, or after a layer that does not exist")
+            display("The layer '{}' is ordered after itself, or after a layer that does not exist", n)
+        }
+        #[doc = "Package `layer` is either invalid, or is an outer layer"]
         InvalidLayerInPackage {
             description("Package `layer` is either invalid, or is an outer layer")
             display("Package `layer` is either invalid, or is an outer layer")
         }
-         #[doc("Either DFML_HOME note set, or no package directory found there")]
+        #[doc = "Packages may only be created within an inner layer"]
+        PackageInOuterLayer(layer: Name) {
+            description("Packages may only be created within an inner layer")
+            display("Packages may only be created within an inner layer, '{}' is an outer layer", layer)
+        }
+        #[doc = "A package extends itself, directly or through other packages"]
+        InvalidPackageExtends(n: Name) {
+            description("A package extends itself, directly or through other packages")
+            display("The package '{}' extends itself, directly or through other packages", n)
+        }
+        #[doc = "A package with the provided name already exists"]
+        PackageExists(n: Name) {
+            description("A package with the provided name already exists")
+            display("A package with the name '{}' already exists", n)
+        }
+        #[doc = "No package directory found; use --packages, set PACKPACK_HOME, or create ~/.packpack/pkgroot"]
         MissingPackageDir {
-            description("Either DFML_HOME note set, or no package directory found there")
-            display("Either DFML_HOME note set, or no package directory found there")
+            description("No package directory found; use --packages, set PACKPACK_HOME, or create ~/.packpack/pkgroot")
+            display("No package directory found; use --packages, set PACKPACK_HOME, or create ~/.packpack/pkgroot")
         }
-        #[doc("No package found with the provided name")]
-        NoSuchPackage(n: Name) {
+        #[doc = "No package found with the provided name"]
+        NoSuchPackage(n: Name, suggestions: Vec<Name>) {
             description("No package found with the provided name")
-            display("No package found with the provided name '{}'", n)
+            display("No package found with the provided name '{}'{}", n, did_you_mean(suggestions))
+        }
+        #[doc = "No layer found with the provided name"]
+        NoSuchLayer(n: Name, suggestions: Vec<Name>) {
+            description("No layer found with the provided name")
+            display("No layer found with the provided name '{}'{}", n, did_you_mean(suggestions))
+        }
+        #[doc = "No layer or package found with the provided name"]
+        NoSuchSelection(n: Name, suggestions: Vec<Name>) {
+            description("No layer or package found with the provided name")
+            display("No layer or package found with the provided name '{}'{}", n, did_you_mean(suggestions))
+        }
+        #[doc = "No installer found with the provided name"]
+        NoSuchInstaller(n: Name, suggestions: Vec<Name>) {
+            description("No installer found with the provided name")
+            display("No installer found with the provided name '{}'{}", n, did_you_mean(suggestions))
+        }
+        #[doc = "Nothing matches the provided pattern"]
+        NoMatchingNames(pattern: String) {
+            description("Nothing matches the provided pattern")
+            display("Nothing matches the pattern '{}'", pattern)
+        }
+        #[doc = "The package does not contain an inner package with the provided name"]
+        NoSuchInnerPackage(package: Name, n: Name, suggestions: Vec<Name>) {
+            description("The package does not contain an inner package with the provided name")
+            display("The package '{}' does not contain an inner package named '{}'{}", package, n, did_you_mean(suggestions))
+        }
+        #[doc = "The package already contains an inner package with the provided name and installer"]
+        InnerPackageExists(package: Name, n: Name, installer: String) {
+            description("The package already contains an inner package with the provided name and installer")
+            display("The package '{}' already contains an inner package named '{}' for the installers '{}'", package, n, installer)
+        }
+        #[doc = "The package is not within the layer it was qualified with"]
+        PackageNotInLayer(package: Name, layer: Name) {
+            description("The package is not within the layer it was qualified with")
+            display("The package '{}' is not within the layer '{}'", package, layer)
+        }
+        #[doc = "The package directory is not within a git repository"]
+        NotARepository(path: PathBuf) {
+            description("The package directory is not within a git repository")
+            display("The package directory {} is not within a git repository", path.to_string_lossy())
+        }
+        #[doc = "The package repository has local commits that are not in its upstream"]
+        RepositoryDiverged(path: PathBuf) {
+            description("The package repository has local commits that are not in its upstream")
+            display("The package repository {} has local commits that are not in its upstream, push or rebase them first", path.to_string_lossy())
+        }
+        #[doc = "The package repository has uncommitted changes"]
+        RepositoryNotClean(path: PathBuf) {
+            description("The package repository has uncommitted changes")
+            display("The package repository {} has uncommitted changes, commit or remove them first", path.to_string_lossy())
+        }
+        #[doc = "A git command failed"]
+        GitCommandFailed(command: String, message: String) {
+            description("A git command failed")
+            display("The command 'git {}' failed: {}", command, message)
         }
-        #[doc("Current platform is unsupported (determined by target_os)")]
+        #[doc = "A package or layer script failed"]
+        ScriptFailed(path: PathBuf, message: String) {
+            description("A package or layer script failed")
+            display("The script {} failed: {}", path.to_string_lossy(), message)
+        }
+        #[doc = "An installer command failed"]
+        InstallerCommandFailed(installer: Name, message: String) {
+            description("An installer command failed")
+            display("The installer '{}' failed: {}", installer, message)
+        }
+        #[doc = "An installer has no way to perform the requested action"]
+        UnsupportedInstallerAction(installer: Name, action: String) {
+            description("An installer has no way to perform the requested action")
+            display("The installer '{}' does not support {}", installer, action)
+        }
+        #[doc = "No installer in an inner package's list is available, or all of them failed"]
+        NoAvailableInstaller(n: Name, installers: String) {
+            description("No installer in an inner package's list is available, or all of them failed")
+            display("No installer could act on the inner package '{}', tried: {}", n, installers)
+        }
+        #[doc = "No installer in an inner package's list reports it as installed"]
+        InnerPackageNotInstalled(n: Name, installers: String) {
+            description("No installer in an inner package's list reports it as installed")
+            display("No installer reports the inner package '{}' as installed, checked: {}", n, installers)
+        }
+        #[doc = "Current platform is unsupported (determined by target_os)"]
         UnsupportedPlatform {
             description("Current platform is unsupported (determined by target_os)")
             display("Current platform is unsupported (determined by target_os)")
         }
-        #[doc("Installer may not use the name 'system'")]
+        #[doc = "Installer may not use the name 'system'"]
         SystemInstallerName {
             description("Installer may not use the name 'system'")
             display("Installer may not use the name 'system'")
         }
-        #[doc("No system installer present for current platform")]
+        #[doc = "No system installer present for current platform"]
         MissingSystemInstaller {
             description("No system installer present for current platform")
             display("No system installer present for current platform")
         }
-        #[doc("A required command was not found")]
+        #[doc = "A required command was not found"]
         MissingRequiredCommand {
             description("A required command was not found")
             display("A required command was not found")
@@ -67,11 +190,22 @@ error_chain! {
         Environment(::std::env::VarError);
         Fmt(::std::fmt::Error);
         Io(::std::io::Error);
+        Json(::serde_json::Error);
         Serialize(::toml::ser::Error);
         Unexpected(::std::convert::Infallible);
+        Yaml(::serde_yaml::Error);
     }
 }
 
+///
+/// A 1-based line and column location within a file.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
 // ------------------------------------------------------------------------------------------------
 // Private Types
 // ------------------------------------------------------------------------------------------------
@@ -80,14 +214,63 @@ error_chain! {
 // Public Functions
 // ------------------------------------------------------------------------------------------------
 
+///
+/// Split a TOML error into its location, if known, and the message without the trailing
+/// " at line X column Y" that the TOML crate appends.
+///
+pub(crate) fn describe_toml_error(err: &::toml::de::Error) -> (Option<Location>, String) {
+    let location = err.line_col().map(|(line, column)| Location {
+        line: line + 1,
+        column: column + 1,
+    });
+    let message = err.to_string();
+    let message = match (location, message.rfind(" at line ")) {
+        (Some(_), Some(index)) => message[..index].to_string(),
+        _ => message,
+    };
+    (location, message)
+}
+
 // ------------------------------------------------------------------------------------------------
 // Implementations
 // ------------------------------------------------------------------------------------------------
 
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+impl Location {
+    pub(crate) fn from_offset(content: &str, offset: usize) -> Self {
+        let before = &content[..offset.min(content.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = match before.rfind('\n') {
+            Some(index) => before[index + 1..].chars().count() + 1,
+            None => before.chars().count() + 1,
+        };
+        Self { line, column }
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // Private Functions
 // ------------------------------------------------------------------------------------------------
 
+fn did_you_mean(suggestions: &[Name]) -> String {
+    match suggestions {
+        [] => String::new(),
+        [one] => format!(", did you mean '{}'?", one),
+        many => format!(
+            ", did you mean one of {}?",
+            many.iter()
+                .map(|name| format!("'{}'", name))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // Modules
 // ------------------------------------------------------------------------------------------------
@@ -7,6 +7,9 @@ More detailed description, with
 
 */
 
+// The `error_chain!` expansion needs more than the default limit.
+#![recursion_limit = "256"]
+
 #[macro_use]
 extern crate error_chain;
 
@@ -16,7 +19,10 @@ extern crate lazy_static;
 #[macro_use]
 extern crate log;
 
+use config::Configuration;
 use error::Result;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
@@ -27,12 +33,25 @@ pub struct Options {
     pub dry_run: bool,
 }
 
+///
+/// The formats in which configuration and reports may be written; `Text` is the human-readable
+/// form, the others are serialized from the structures in the `report` module.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Toml,
+    Json,
+    Yaml,
+}
+
 pub trait Installable {
-    fn install(&self, options: &Options) -> Result<()>;
+    fn install(&self, config: &Configuration, options: &Options) -> Result<()>;
 
-    fn update(&self, options: &Options) -> Result<()>;
+    fn update(&self, config: &Configuration, options: &Options) -> Result<()>;
 
-    fn delete(&self, options: &Options) -> Result<()>;
+    fn delete(&self, config: &Configuration, options: &Options) -> Result<()>;
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -47,6 +66,35 @@ pub trait Installable {
 // Implementations
 // ------------------------------------------------------------------------------------------------
 
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                OutputFormat::Text => "text",
+                OutputFormat::Toml => "toml",
+                OutputFormat::Json => "json",
+                OutputFormat::Yaml => "yaml",
+            }
+        )
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "toml" => Ok(OutputFormat::Toml),
+            "json" => Ok(OutputFormat::Json),
+            "yaml" => Ok(OutputFormat::Yaml),
+            _ => Err(format!("invalid value '{}' for output format", s)),
+        }
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // Private Functions
 // ------------------------------------------------------------------------------------------------
@@ -55,6 +103,8 @@ pub trait Installable {
 // Modules
 // ------------------------------------------------------------------------------------------------
 
+pub mod backend;
+
 pub mod config;
 
 pub mod error;
@@ -62,3 +112,16 @@ pub mod error;
 pub mod name;
 
 pub mod package;
+
+pub mod report;
+
+pub mod selection;
+
+pub mod state;
+
+pub mod style;
+
+pub mod sync;
+
+#[cfg(test)]
+mod testing;
@@ -1,25 +1,74 @@
 /*!
-One-line description.
+Names for packages, layers, installers, and platforms.
 
-More detailed description, with
+A `Name` keeps the spelling it was written with for display, but compares, and hashes, without
+regard to case so that `rust` finds the package named `Rust`. A `QualifiedName` adds an optional
+layer and inner package to a package name, as `layer/package:inner`. `NamePattern` and
+`QualifiedPattern` are the same, but each part may also be a glob pattern such as `dev-*`.
 
 # Example
 
+```rust
+use packpack::name::{Name, QualifiedName};
+
+let name: Name = "Rust".parse().unwrap();
+assert_eq!(name, "rust".parse().unwrap());
+assert_eq!(name.to_string(), "Rust");
+
+let qualified: QualifiedName = "programming-languages/rust:clippy-preview".parse().unwrap();
+assert_eq!(qualified.layer, Some("programming-languages".parse().unwrap()));
+assert_eq!(qualified.name, name);
+assert_eq!(qualified.inner, Some("clippy-preview".parse().unwrap()));
+```
+
 */
 
-use serde::export::Formatter;
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
+use std::fmt::Formatter;
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
 // ------------------------------------------------------------------------------------------------
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(transparent)]
 pub struct Name(String);
 
+///
+/// A package name, optionally qualified by the layer it must belong to and the single inner
+/// package to act upon.
+///
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct QualifiedName {
+    pub layer: Option<Name>,
+    pub name: Name,
+    pub inner: Option<Name>,
+}
+
+///
+/// A glob pattern, using `*`, `?`, and `[...]`, matched against names without regard to case. A
+/// pattern with no special characters is simply a name.
+///
+#[derive(Clone, Debug)]
+pub struct NamePattern {
+    source: String,
+    pattern: glob::Pattern,
+}
+
+///
+/// A `QualifiedName` in which each part may be a pattern; `*/fonts` is any package named `fonts`
+/// in any layer.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct QualifiedPattern {
+    pub layer: Option<NamePattern>,
+    pub name: NamePattern,
+    pub inner: Option<NamePattern>,
+}
+
 // ------------------------------------------------------------------------------------------------
 // Private Types
 // ------------------------------------------------------------------------------------------------
@@ -50,9 +99,35 @@ impl FromStr for Name {
     }
 }
 
-impl Into<String> for Name {
-    fn into(self) -> String {
-        self.0.clone()
+impl PartialEq for Name {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_lowercase() == other.0.to_lowercase()
+    }
+}
+
+impl Eq for Name {}
+
+impl PartialOrd for Name {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Name {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.to_lowercase().cmp(&other.0.to_lowercase())
+    }
+}
+
+impl Hash for Name {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_lowercase().hash(state)
+    }
+}
+
+impl From<Name> for String {
+    fn from(val: Name) -> Self {
+        val.0.clone()
     }
 }
 
@@ -62,6 +137,228 @@ impl Name {
             && is_valid_first_char(s.chars().next().unwrap())
             && s[1..].chars().all(is_valid_rest_char)
     }
+
+    ///
+    /// The `candidates` that are close enough to this name to be a likely misspelling, closest
+    /// first.
+    ///
+    pub fn suggestions<'a>(&self, candidates: impl Iterator<Item = &'a Name>) -> Vec<Name> {
+        let this = self.0.to_lowercase();
+        let limit = std::cmp::max(1, this.chars().count() / 3);
+        let mut close: Vec<(usize, &Name)> = candidates
+            .map(|candidate| {
+                (
+                    strsim::damerau_levenshtein(&this, &candidate.0.to_lowercase()),
+                    candidate,
+                )
+            })
+            .filter(|(distance, _)| *distance <= limit)
+            .collect();
+        close.sort_by_key(|(distance, candidate)| (*distance, candidate.0.clone()));
+        close.dedup_by(|a, b| a.1 == b.1);
+        close
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, candidate)| candidate.clone())
+            .collect()
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Display for QualifiedName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(layer) = &self.layer {
+            write!(f, "{}/", layer)?;
+        }
+        write!(f, "{}", self.name)?;
+        if let Some(inner) = &self.inner {
+            write!(f, ":{}", inner)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for QualifiedName {
+    type Err = crate::error::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (layer, rest) = match s.split_once('/') {
+            Some((layer, rest)) => (Some(layer.parse()?), rest),
+            None => (None, s),
+        };
+        let (name, inner) = match rest.split_once(':') {
+            Some((name, inner)) => (name.parse()?, Some(inner.parse()?)),
+            None => (rest.parse()?, None),
+        };
+        Ok(Self { layer, name, inner })
+    }
+}
+
+impl From<Name> for QualifiedName {
+    fn from(name: Name) -> Self {
+        Self {
+            layer: None,
+            name,
+            inner: None,
+        }
+    }
+}
+
+impl QualifiedName {
+    /// The bare name, if this name has neither a layer nor an inner package.
+    pub fn as_simple(&self) -> Option<&Name> {
+        match (&self.layer, &self.inner) {
+            (None, None) => Some(&self.name),
+            _ => None,
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Display for NamePattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl FromStr for NamePattern {
+    type Err = crate::error::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let valid = if is_literal(s) {
+            Name::is_valid(s)
+        } else {
+            !s.is_empty()
+                && s.chars()
+                    .all(|c| is_valid_rest_char(c) || is_pattern_char(c))
+        };
+        match glob::Pattern::new(s) {
+            Ok(pattern) if valid => Ok(Self {
+                source: s.to_string(),
+                pattern,
+            }),
+            _ => Err(crate::error::ErrorKind::InvalidName(s.to_string()).into()),
+        }
+    }
+}
+
+impl PartialEq for NamePattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source.to_lowercase() == other.source.to_lowercase()
+    }
+}
+
+impl From<Name> for NamePattern {
+    fn from(name: Name) -> Self {
+        Self {
+            pattern: glob::Pattern::new(&glob::Pattern::escape(&name.0)).unwrap(),
+            source: name.0,
+        }
+    }
+}
+
+impl NamePattern {
+    /// Returns `true` if this pattern has no special characters, and so matches only one name.
+    pub fn is_literal(&self) -> bool {
+        is_literal(&self.source)
+    }
+
+    /// The single name this pattern matches, if it is literal.
+    pub fn as_name(&self) -> Option<Name> {
+        if self.is_literal() {
+            Some(Name(self.source.clone()))
+        } else {
+            None
+        }
+    }
+
+    pub fn matches(&self, name: &Name) -> bool {
+        self.pattern.matches_with(
+            &name.0,
+            glob::MatchOptions {
+                case_sensitive: false,
+                require_literal_separator: false,
+                require_literal_leading_dot: false,
+            },
+        )
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl Display for QualifiedPattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(layer) = &self.layer {
+            write!(f, "{}/", layer)?;
+        }
+        write!(f, "{}", self.name)?;
+        if let Some(inner) = &self.inner {
+            write!(f, ":{}", inner)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for QualifiedPattern {
+    type Err = crate::error::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (layer, rest) = match s.split_once('/') {
+            Some((layer, rest)) => (Some(layer.parse()?), rest),
+            None => (None, s),
+        };
+        let (name, inner) = match rest.split_once(':') {
+            Some((name, inner)) => (name.parse()?, Some(inner.parse()?)),
+            None => (rest.parse()?, None),
+        };
+        Ok(Self { layer, name, inner })
+    }
+}
+
+impl From<NamePattern> for QualifiedPattern {
+    fn from(name: NamePattern) -> Self {
+        Self {
+            layer: None,
+            name,
+            inner: None,
+        }
+    }
+}
+
+impl From<QualifiedName> for QualifiedPattern {
+    fn from(name: QualifiedName) -> Self {
+        Self {
+            layer: name.layer.map(NamePattern::from),
+            name: name.name.into(),
+            inner: name.inner.map(NamePattern::from),
+        }
+    }
+}
+
+impl QualifiedPattern {
+    /// The bare name pattern, if this pattern has neither a layer nor an inner package.
+    pub fn as_simple(&self) -> Option<&NamePattern> {
+        match (&self.layer, &self.inner) {
+            (None, None) => Some(&self.name),
+            _ => None,
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+const MAX_SUGGESTIONS: usize = 3;
+
+fn is_literal(s: &str) -> bool {
+    !s.chars().any(is_pattern_char)
+}
+
+fn is_pattern_char(c: char) -> bool {
+    c == '*' || c == '?' || c == '[' || c == ']' || c == '!'
 }
 
 fn is_valid_first_char(c: char) -> bool {
@@ -73,9 +370,76 @@ fn is_valid_rest_char(c: char) -> bool {
 }
 
 // ------------------------------------------------------------------------------------------------
-// Private Functions
+// Modules
 // ------------------------------------------------------------------------------------------------
 
 // ------------------------------------------------------------------------------------------------
-// Modules
+// Unit Tests
 // ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+    use std::collections::HashMap;
+
+    fn name(s: &str) -> Name {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        assert_eq!(name("Rust"), name("rust"));
+        assert_ne!(name("Rust"), name("rusty"));
+        let mut map: HashMap<Name, usize> = Default::default();
+        let _ = map.insert(name("Rust"), 1);
+        assert_eq!(map.get(&name("RUST")), Some(&1));
+        assert_eq!(map.keys().next().unwrap().to_string(), "Rust");
+    }
+
+    #[test]
+    fn test_qualified_names() {
+        let qualified: QualifiedName = "Rust".parse().unwrap();
+        assert_eq!(qualified.as_simple(), Some(&name("Rust")));
+        let qualified: QualifiedName = "Rust:clippy".parse().unwrap();
+        assert_eq!(qualified.as_simple(), None);
+        assert_eq!(qualified.to_string(), "Rust:clippy");
+        assert!("a/b/c".parse::<QualifiedName>().is_err());
+        assert!("/b".parse::<QualifiedName>().is_err());
+        assert!("b:".parse::<QualifiedName>().is_err());
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let candidates = [name("Rust"), name("Ruby"), name("Python")];
+        assert_eq!(
+            name("rsut").suggestions(candidates.iter()),
+            vec![name("Rust")]
+        );
+        assert_eq!(
+            name("rub").suggestions(candidates.iter()),
+            vec![name("Ruby")]
+        );
+        assert!(name("java").suggestions(candidates.iter()).is_empty());
+    }
+
+    #[test]
+    fn test_name_patterns() {
+        let pattern: NamePattern = "dev*".parse().unwrap();
+        assert!(!pattern.is_literal());
+        assert!(pattern.matches(&name("development")));
+        assert!(pattern.matches(&name("Dev-Tools")));
+        assert!(!pattern.matches(&name("system")));
+
+        let pattern: NamePattern = "Rust".parse().unwrap();
+        assert_eq!(pattern.as_name(), Some(name("Rust")));
+        assert!(pattern.matches(&name("rust")));
+
+        let qualified: QualifiedPattern = "*/fonts".parse().unwrap();
+        assert!(qualified.as_simple().is_none());
+        assert_eq!(qualified.to_string(), "*/fonts");
+        assert!("dev/*/x".parse::<QualifiedPattern>().is_err());
+        assert!("[".parse::<NamePattern>().is_err());
+    }
+}
@@ -0,0 +1,170 @@
+/*!
+Scaffolds a new package in the package root; a directory named for the package holding a
+package file with, optionally, a first inner package and a pair of install scripts to edit.
+
+The layer must be an inner layer, and the installer, if any, must be configured or be `system`.
+A package may not be created with the name of one that already exists in any package root.
+
+*/
+
+use crate::config::Configuration;
+use crate::error::{ErrorKind, Result};
+use crate::name::Name;
+use crate::package::{
+    get_package_dir, get_packages, InnerPackage, Package, ScriptSet, PACKAGE_FILE_NAME,
+    SYSTEM_INSTALLER,
+};
+use std::path::Path;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+pub(super) fn create_package(
+    config: &Configuration,
+    name: &Name,
+    layer: &Name,
+    installer: Option<&Name>,
+    with_scripts: bool,
+) -> Result<Package> {
+    debug!(
+        "create_package({:?}, {:?}, {:?}, {})",
+        name.to_string(),
+        layer.to_string(),
+        installer.map(|installer| installer.to_string()),
+        with_scripts
+    );
+
+    check_layer(config, layer)?;
+    if let Some(installer) = installer {
+        check_installer(config, installer)?;
+    }
+
+    let package_dir = get_package_dir(config)?.join(name.to_string());
+    if package_dir.exists() || get_packages(config)?.contains_key(name) {
+        error!("package {} already exists", name);
+        return Err(ErrorKind::PackageExists(name.clone()).into());
+    }
+
+    let mut package = Package {
+        path: package_dir.join(PACKAGE_FILE_NAME),
+        origins: Default::default(),
+        extends: None,
+        name: name.clone(),
+        layer: layer.clone(),
+        description: None,
+        tags: Default::default(),
+        homepage: None,
+        maintainer: None,
+        packages: Default::default(),
+        on_install: None,
+        on_update: None,
+        on_delete: None,
+        requires: Default::default(),
+    };
+    if let Some(installer) = installer {
+        package.packages.push(InnerPackage {
+            name: name.clone(),
+            installer: installer.clone().into(),
+            priority: Default::default(),
+            is_app: false,
+            additional_arguments: Default::default(),
+            platforms: Default::default(),
+            names: Default::default(),
+            version: None,
+        });
+    }
+    if with_scripts {
+        package.on_install = Some(ScriptSet {
+            before: Some(BEFORE_INSTALL_SCRIPT.into()),
+            after: Some(AFTER_INSTALL_SCRIPT.into()),
+        });
+    }
+
+    let content = toml::to_string(&package)?;
+
+    std::fs::create_dir_all(&package_dir)?;
+    std::fs::write(&package.path, content)?;
+    if with_scripts {
+        write_script(&package_dir, BEFORE_INSTALL_SCRIPT, name, "before")?;
+        write_script(&package_dir, AFTER_INSTALL_SCRIPT, name, "after")?;
+    }
+    info!("created package {} in {:?}", name, package_dir);
+
+    Ok(package)
+}
+
+/// Check that `installer` is either `system` or one that is configured.
+pub(super) fn check_installer(config: &Configuration, installer: &Name) -> Result<()> {
+    if installer.to_string().eq_ignore_ascii_case(SYSTEM_INSTALLER)
+        || config.installer(installer).is_some()
+    {
+        Ok(())
+    } else {
+        Err(ErrorKind::NoSuchInstaller(
+            installer.clone(),
+            installer.suggestions(config.all_installers().map(|(id, _)| id)),
+        )
+        .into())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+const BEFORE_INSTALL_SCRIPT: &str = "before-install.sh";
+
+const AFTER_INSTALL_SCRIPT: &str = "after-install.sh";
+
+fn check_layer(config: &Configuration, layer: &Name) -> Result<()> {
+    match config.find_layer(layer) {
+        Some(found) if found.is_inner() => Ok(()),
+        Some(_) => {
+            error!("layer {} is an outer layer", layer);
+            Err(ErrorKind::PackageInOuterLayer(layer.clone()).into())
+        }
+        None => {
+            let layers: Vec<&Name> = config
+                .layers()
+                .flat_map(|layer| layer.all_names())
+                .collect();
+            Err(ErrorKind::NoSuchLayer(layer.clone(), layer.suggestions(layers.into_iter())).into())
+        }
+    }
+}
+
+fn write_script(package_dir: &Path, file_name: &str, name: &Name, when: &str) -> Result<()> {
+    let path = package_dir.join(file_name);
+    std::fs::write(
+        &path,
+        format!(
+            r#"#!/usr/bin/env bash
+PACKAGE_NAME="{name}"
+source "$HOME/.packpack/bin/logging.sh"
+
+log-info "{when} installing {name}"
+"#,
+            name = name,
+            when = when
+        ),
+    )?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,242 @@
+/*!
+Adds inner packages to, and removes them from, a package's own file in place.
+
+The file is edited as a document, not re-serialized, so that comments, formatting, and the order
+of values elsewhere in the file are kept; the result is checked by parsing it again before it is
+written. Only the named package's own file is changed, so an inner package that comes from a
+package it extends, from an overlay file, or from a later package root cannot be removed here.
+
+*/
+
+use crate::config::Configuration;
+use crate::error::{describe_toml_error, ErrorKind, Result};
+use crate::name::Name;
+use crate::package::create::check_installer;
+use crate::package::{InnerPackage, Package, PackageOverlay, EXTENDS};
+use std::path::{Path, PathBuf};
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table, Value};
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+pub(super) fn add_inner_package(
+    config: &Configuration,
+    package_name: &Name,
+    inner: &InnerPackage,
+) -> Result<Package> {
+    debug!(
+        "add_inner_package({:?}, {:?} ({}))",
+        package_name.to_string(),
+        inner.name.to_string(),
+        inner.installer
+    );
+    let (package, path, own) = base_package(config, package_name)?;
+    for installer in inner.installer.iter() {
+        check_installer(config, installer)?;
+    }
+    if own
+        .packages
+        .iter()
+        .any(|existing| existing.name == inner.name && existing.installer == inner.installer)
+    {
+        return Err(ErrorKind::InnerPackageExists(
+            package.name.clone(),
+            inner.name.clone(),
+            inner.installer.to_string(),
+        )
+        .into());
+    }
+
+    let mut document = read_document(&path)?;
+    let table = inner_table(inner);
+    match document.get_mut(PACKAGES) {
+        Some(Item::ArrayOfTables(array)) => array.push(table),
+        Some(Item::Value(Value::Array(array))) if !array.is_empty() => {
+            array.push(table.into_inline_table())
+        }
+        _ => {
+            let mut array = ArrayOfTables::new();
+            array.push(table);
+            document.insert(PACKAGES, Item::ArrayOfTables(array));
+        }
+    }
+
+    write_document(config, &path, &document)
+}
+
+pub(super) fn remove_inner_package(
+    config: &Configuration,
+    package_name: &Name,
+    inner_name: &Name,
+    installer: Option<&Name>,
+) -> Result<Package> {
+    debug!(
+        "remove_inner_package({:?}, {:?}, {:?})",
+        package_name.to_string(),
+        inner_name.to_string(),
+        installer.map(|installer| installer.to_string())
+    );
+    let (path, remove) = own_inner_packages(config, package_name, inner_name, installer)?;
+    let mut document = read_document(&path)?;
+    match document.get_mut(PACKAGES) {
+        Some(Item::ArrayOfTables(array)) => {
+            let mut index = 0;
+            array.retain(|_| {
+                index += 1;
+                !remove[index - 1]
+            });
+        }
+        Some(Item::Value(Value::Array(array))) => {
+            let mut index = 0;
+            array.retain(|_| {
+                index += 1;
+                !remove[index - 1]
+            });
+        }
+        _ => unreachable!("the file has the inner package to remove"),
+    }
+
+    write_document(config, &path, &document)
+}
+
+///
+/// The path of the named package's own file, and for each inner package in that file whether
+/// it is named `inner_name` and, if provided, has `installer`; it is an error if none match.
+/// The document and the parsed file list the inner packages in the same order.
+///
+pub(super) fn own_inner_packages(
+    config: &Configuration,
+    package_name: &Name,
+    inner_name: &Name,
+    installer: Option<&Name>,
+) -> Result<(PathBuf, Vec<bool>)> {
+    let (package, path, own) = base_package(config, package_name)?;
+    let is_match = |inner: &InnerPackage| {
+        inner.name == *inner_name
+            && installer
+                .map(|installer| inner.installer.contains(installer))
+                .unwrap_or(true)
+    };
+    if !own.packages.iter().any(is_match) {
+        return Err(ErrorKind::NoSuchInnerPackage(
+            package.name.clone(),
+            inner_name.clone(),
+            inner_name.suggestions(own.packages.iter().map(|inner| &inner.name)),
+        )
+        .into());
+    }
+    Ok((path, own.packages.iter().map(is_match).collect()))
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+const PACKAGES: &str = "packages";
+
+///
+/// The named package, the path of its own file, and the content of that file alone; without any
+/// overrides from later package roots, overlay files, or the package it extends, as that is the
+/// file that is edited.
+///
+fn base_package(
+    config: &Configuration,
+    package_name: &Name,
+) -> Result<(Package, PathBuf, PackageOverlay)> {
+    let package = Package::load(config, package_name)?;
+    let path = package.path.clone();
+    let own = PackageOverlay::load(&path)?;
+    Ok((package, path, own))
+}
+
+fn read_document(path: &Path) -> Result<DocumentMut> {
+    let content = std::fs::read_to_string(path)?;
+    content.parse().map_err(|e: toml_edit::TomlError| {
+        ErrorKind::InvalidConfigFile(path.to_path_buf(), None, e.to_string()).into()
+    })
+}
+
+fn write_document(config: &Configuration, path: &Path, document: &DocumentMut) -> Result<Package> {
+    let content = document.to_string();
+    let checked = if document.contains_key(EXTENDS) {
+        toml::from_str::<PackageOverlay>(&content).map(|_| ())
+    } else {
+        toml::from_str::<Package>(&content).map(|_| ())
+    };
+    if let Err(e) = checked {
+        let (location, message) = describe_toml_error(&e);
+        return Err(ErrorKind::InvalidConfigFile(path.to_path_buf(), location, message).into());
+    }
+    std::fs::write(path, content)?;
+    Package::load_from(config, path)
+}
+
+fn inner_table(inner: &InnerPackage) -> Table {
+    let mut table = Table::new();
+    table.insert("name", toml_edit::value(inner.name.to_string()));
+    let installers: Vec<String> = inner.installer.iter().map(|i| i.to_string()).collect();
+    if installers.len() == 1 {
+        table.insert("installer", toml_edit::value(&installers[0]));
+    } else {
+        table.insert(
+            "installer",
+            toml_edit::value(installers.iter().collect::<toml_edit::Array>()),
+        );
+    }
+    if !inner.priority.is_normal() {
+        table.insert(
+            "priority",
+            toml_edit::value(format!("{:?}", inner.priority)),
+        );
+    }
+    if inner.is_app {
+        table.insert("is_app", toml_edit::value(true));
+    }
+    if !inner.additional_arguments.is_empty() {
+        table.insert(
+            "additional_arguments",
+            toml_edit::value(
+                inner
+                    .additional_arguments
+                    .iter()
+                    .collect::<toml_edit::Array>(),
+            ),
+        );
+    }
+    if !inner.platforms.is_empty() {
+        table.insert(
+            "platforms",
+            toml_edit::value(
+                inner
+                    .platforms
+                    .iter()
+                    .map(|platform| platform.to_string())
+                    .collect::<toml_edit::Array>(),
+            ),
+        );
+    }
+    if let Some(version) = &inner.version {
+        table.insert("version", toml_edit::value(version));
+    }
+    if !inner.names.is_empty() {
+        let mut names = toml_edit::InlineTable::new();
+        for (installer, name) in &inner.names {
+            names.insert(installer.to_string(), name.into());
+        }
+        table.insert("names", toml_edit::value(names));
+    }
+    table
+}
@@ -1,56 +1,111 @@
 use crate::error::Result;
 use crate::package::{InnerPackagePriority, Package, ScriptSet};
-use std::borrow::Cow;
+use crate::style::Styler;
 use std::io::Write;
 
 // ------------------------------------------------------------------------------------------------
 // Public Functions
 // ------------------------------------------------------------------------------------------------
 
-pub(super) fn inspect_package(package: &Package, w: &mut impl Write, colored: bool) -> Result<()> {
+pub(super) fn inspect_package(
+    package: &Package,
+    w: &mut impl Write,
+    styler: &Styler,
+) -> Result<()> {
     debug!(
-        "> inspect_package({:?}, .., {})",
+        "> inspect_package({:?}, .., {:?})",
         package.name.to_string(),
-        colored
+        styler
     );
-    writeln!(w, "{}", bold_string("File", colored))?;
+    writeln!(w, "{}", styler.heading("File"))?;
     writeln!(w, "\t{}", package.path.to_string_lossy())?;
 
-    writeln!(w, "{}", bold_string("Name", colored))?;
+    let overrides = package.overrides();
+    if !overrides.is_empty() {
+        writeln!(w, "{}", styler.heading("Overridden by"))?;
+        for path in overrides {
+            writeln!(w, "\t{}", path.to_string_lossy())?;
+        }
+    }
+
+    writeln!(w, "{}", styler.heading("Name"))?;
     writeln!(
         w,
         "\t{}, in layer {}",
-        package.name.to_string(),
-        package.layer.to_string()
+        package.name,
+        styler.dimmed(&package.layer.to_string())
     )?;
 
+    if let Some(extends) = &package.extends {
+        writeln!(w, "{}", styler.heading("Extends"))?;
+        writeln!(w, "\t{}", extends)?;
+    }
+
+    if let Some(description) = &package.description {
+        writeln!(w, "{}", styler.heading("Description"))?;
+        writeln!(w, "\t{}", description)?;
+    }
+
+    if let Some(homepage) = &package.homepage {
+        writeln!(w, "{}", styler.heading("Homepage"))?;
+        writeln!(w, "\t{}", homepage)?;
+    }
+
+    if let Some(maintainer) = &package.maintainer {
+        writeln!(w, "{}", styler.heading("Maintainer"))?;
+        writeln!(w, "\t{}", maintainer)?;
+    }
+
+    if !package.tags.is_empty() {
+        writeln!(w, "{}", styler.heading("Tags"))?;
+        writeln!(
+            w,
+            "\t{}",
+            package
+                .tags
+                .iter()
+                .map(|tag| tag.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        )?;
+    }
+
     if !package.requires.is_empty() {
-        writeln!(w, "{}", bold_string("Requires commands", colored))?;
+        writeln!(w, "{}", styler.heading("Requires commands"))?;
         for command in &package.requires {
             writeln!(w, "* {}", command)?;
         }
     }
 
     if let Some(script_set) = &package.on_install {
-        writeln!(w, "{}", bold_string("On Install", colored))?;
+        writeln!(w, "{}", styler.heading("On Install"))?;
         write_scripts(w, script_set)?;
     }
 
     if let Some(script_set) = &package.on_update {
-        writeln!(w, "{}", bold_string("On Update", colored))?;
+        writeln!(w, "{}", styler.heading("On Update"))?;
         write_scripts(w, script_set)?;
     }
 
     if let Some(script_set) = &package.on_delete {
-        writeln!(w, "{}", bold_string("On Delete", colored))?;
+        writeln!(w, "{}", styler.heading("On Delete"))?;
         write_scripts(w, script_set)?;
     }
 
-    writeln!(w, "{}", bold_string("Packages", colored))?;
+    writeln!(w, "{}", styler.heading("Packages"))?;
+    let origins = &package.origins;
     let mut packages = package.packages.clone();
     packages.sort_by(|a, b| b.priority.cmp(&a.priority));
     for package in packages {
-        writeln!(w, "* {} ({})", package.name, package.installer)?;
+        writeln!(
+            w,
+            "* {} ({})",
+            package.name,
+            styler.installer(&package.installer.to_string())
+        )?;
+        if let Some(origin) = origins.get(&package.origin_key()) {
+            writeln!(w, "  * from: {}", origin.to_string_lossy())?;
+        }
         if !package.additional_arguments.is_empty() {
             writeln!(
                 w,
@@ -64,6 +119,17 @@ pub(super) fn inspect_package(package: &Package, w: &mut impl Write, colored: bo
         if !package.platforms.is_empty() {
             writeln!(w, "  * only for platforms: {:?}", package.platforms)?;
         }
+        if let Some(version) = &package.version {
+            writeln!(w, "  * version: {}", version)?;
+        }
+        for (installer, name) in &package.names {
+            writeln!(
+                w,
+                "  * named {} for {}",
+                name,
+                styler.installer(&installer.to_string())
+            )?;
+        }
     }
 
     writeln!(w)?;
@@ -74,16 +140,6 @@ pub(super) fn inspect_package(package: &Package, w: &mut impl Write, colored: bo
 // Private Functions
 // ------------------------------------------------------------------------------------------------
 
-#[inline]
-fn bold_string(s: &str, colored: bool) -> Cow<str> {
-    use ansi_term::Style;
-    if colored {
-        Style::new().bold().paint(s).to_string().into()
-    } else {
-        s.into()
-    }
-}
-
 fn write_scripts(w: &mut impl Write, script_set: &ScriptSet) -> Result<()> {
     if let Some(script) = &script_set.before {
         writeln!(
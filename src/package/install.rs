@@ -1,26 +1,46 @@
-use crate::error::Result;
+use crate::error::{ErrorKind, Result};
 
 // ------------------------------------------------------------------------------------------------
 // Public Types
 // ------------------------------------------------------------------------------------------------
 
-use crate::package::{InnerPackage, Package, ScriptSet};
+use crate::backend::{InstallRequest, Registry};
+use crate::config::{Configuration, Layer};
+use crate::name::Name;
+use crate::package::{get_package_dir, InnerPackage, Package, ScriptSet};
+use crate::state::Ledger;
 use crate::Options;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-#[derive(Clone, Debug)]
-pub(super) enum Action {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Action {
     Install,
     Update,
     Delete,
 }
 
+///
+/// The installer backends, and the ledger, shared by every package that a single action acts
+/// upon; each is loaded once, when the action starts.
+///
+#[derive(Debug)]
+pub(crate) struct Session {
+    registry: Registry,
+    ledger: Ledger,
+}
+
 // ------------------------------------------------------------------------------------------------
 // Public Functions
 // ------------------------------------------------------------------------------------------------
 
-pub(super) fn install_action(action: Action, package: &Package, options: &Options) -> Result<()> {
+pub(crate) fn install_action(
+    action: Action,
+    package: &Package,
+    config: &Configuration,
+    session: &mut Session,
+    options: &Options,
+) -> Result<()> {
     debug!(
         "install_action({:?}, {:?}, {:?})",
         action,
@@ -28,23 +48,94 @@ pub(super) fn install_action(action: Action, package: &Package, options: &Option
         options
     );
 
-    check_requires(&package.requires, &options)?;
+    check_requires(&package.requires, options)?;
 
+    let base_path = package.path.parent().unwrap_or_else(|| Path::new(""));
     do_action(
         &action,
-        &package.path,
+        base_path,
+        &package.name,
         &package.packages,
+        config,
+        session,
         match action {
             Action::Install => &package.on_install,
             Action::Update => &package.on_update,
             Action::Delete => &package.on_delete,
         },
-        &options,
+        options,
     )?;
 
     Ok(())
 }
 
+///
+/// Perform `inner`, which acts on the packages within `layer`, surrounded by the layer's own
+/// required command checks and scripts for `action`.
+///
+pub(crate) fn layer_action<F>(
+    action: Action,
+    layer: &Layer,
+    config: &Configuration,
+    options: &Options,
+    inner: F,
+) -> Result<()>
+where
+    F: FnOnce() -> Result<()>,
+{
+    debug!(
+        "layer_action({:?}, {:?}, {:?})",
+        action, layer.name, options
+    );
+
+    check_requires(&layer.requires, options)?;
+
+    let script_set = match action {
+        Action::Install => &layer.on_install,
+        Action::Update => &layer.on_update,
+        Action::Delete => &layer.on_delete,
+    };
+    let base_path = match script_set {
+        Some(_) => get_package_dir(config)?,
+        None => PathBuf::new(),
+    };
+
+    if let Some(path) = script_set.as_ref().and_then(|s| s.before.as_ref()) {
+        run_script(&base_path, path, options)?;
+    }
+
+    inner()?;
+
+    if let Some(path) = script_set.as_ref().and_then(|s| s.after.as_ref()) {
+        run_script(&base_path, path, options)?;
+    }
+
+    Ok(())
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Session {
+    pub(crate) fn for_config(config: &Configuration) -> Result<Self> {
+        Ok(Self {
+            registry: Registry::for_config(config),
+            ledger: Ledger::for_config(config)?,
+        })
+    }
+}
+
+impl Action {
+    fn verb(&self) -> &'static str {
+        match self {
+            Action::Install => "install",
+            Action::Update => "update",
+            Action::Delete => "delete",
+        }
+    }
+}
+
 // ------------------------------------------------------------------------------------------------
 // Private Functions
 // ------------------------------------------------------------------------------------------------
@@ -52,7 +143,7 @@ pub(super) fn install_action(action: Action, package: &Package, options: &Option
 fn check_requires(requires: &[String], _: &Options) -> Result<()> {
     debug!("> check_requires({:?}, ..)", requires);
     for required_cmd in requires {
-        let cmd_path = match which::which(&required_cmd) {
+        let cmd_path = match which::which(required_cmd) {
             Ok(cmd_path) => cmd_path,
             Err(err) => {
                 error!("error finding command '{}': {:?}", required_cmd, err);
@@ -64,10 +155,14 @@ fn check_requires(requires: &[String], _: &Options) -> Result<()> {
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn do_action(
     action: &Action,
-    base_path: &PathBuf,
+    base_path: &Path,
+    package_name: &Name,
     packages: &[InnerPackage],
+    config: &Configuration,
+    session: &mut Session,
     script_set: &Option<ScriptSet>,
     options: &Options,
 ) -> Result<()> {
@@ -80,7 +175,7 @@ fn do_action(
     }
 
     for package in packages {
-        install_inner_package(action, package, options)?;
+        install_inner_package(action, package_name, package, config, session, options)?;
     }
 
     if let Some(script_set) = script_set {
@@ -92,15 +187,12 @@ fn do_action(
     Ok(())
 }
 
-fn run_script(base_path: &PathBuf, script_path: &PathBuf, options: &Options) -> Result<()> {
+fn run_script(base_path: &Path, script_path: &Path, options: &Options) -> Result<()> {
     trace!("> > run_script({:?}, {:?}, ..)", base_path, script_path);
     let script_path = if script_path.is_absolute() {
-        script_path.clone()
+        script_path.to_path_buf()
     } else {
-        match base_path.parent() {
-            None => script_path.clone(),
-            Some(base_path) => base_path.join(script_path),
-        }
+        base_path.join(script_path)
     }
     .canonicalize()?;
     trace!("> > > full script path: {:?}", script_path);
@@ -121,26 +213,189 @@ fn run_script(base_path: &PathBuf, script_path: &PathBuf, options: &Options) ->
             script_path
         );
     } else {
-        Command::new(&shell.to_string_lossy().to_string())
-            .arg(&script_path.to_string_lossy().to_string())
+        let output = Command::new(shell.to_string_lossy().to_string())
+            .arg(script_path.to_string_lossy().to_string())
             .output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            error!(
+                "script {:?} failed, {}: {}",
+                script_path, output.status, stderr
+            );
+            return Err(ErrorKind::ScriptFailed(
+                script_path,
+                if stderr.is_empty() {
+                    output.status.to_string()
+                } else {
+                    stderr
+                },
+            )
+            .into());
+        }
     }
     Ok(())
 }
 
+///
+/// Act on `package` with the first of its installers that is available and succeeds. Updates and
+/// deletes use only the installer recorded in `ledger` as having installed it or, with no record,
+/// only the first available installer that reports it as installed; they never fall back to
+/// another installer, which could act on a package of the same name that it did not install. A
+/// delete with no such installer does nothing, and an update fails. The ledger is changed to
+/// match once an installer succeeds.
+///
 fn install_inner_package(
     action: &Action,
+    package_name: &Name,
     package: &InnerPackage,
-    _options: &Options,
+    config: &Configuration,
+    session: &mut Session,
+    options: &Options,
 ) -> Result<()> {
     trace!(
-        "> > install_inner_package({:?}, {:?} ({}), ..)",
+        "> > install_inner_package({:?}, {:?}, {:?} ({}), ..)",
         action,
+        package_name.to_string(),
         package.name.to_string(),
-        package.installer.to_string(),
+        package.installer,
     );
 
-    // find installer
+    let target_os = &config.platform().target_os;
+    if !package.platforms.is_empty() && !package.platforms.contains(target_os) {
+        debug!("> > > not for platform {}, skipping", target_os);
+        return Ok(());
+    }
 
-    Ok(())
+    let Session { registry, ledger } = session;
+    let is_change = *action != Action::Install;
+    let recorded = match action {
+        Action::Install => None,
+        _ => ledger
+            .find(package_name, package)
+            .map(|entry| (entry.installer.clone(), entry.name.clone())),
+    };
+    let candidates: Vec<Name> = match &recorded {
+        Some((installer, _)) => {
+            trace!("> > > installed by {}", installer);
+            vec![installer.clone()]
+        }
+        None => package
+            .candidate_installers(config)
+            .into_iter()
+            .cloned()
+            .collect(),
+    };
+
+    for installer_name in &candidates {
+        let backend = match registry.get(installer_name) {
+            Some(backend) if backend.is_available() => backend,
+            _ => {
+                debug!("> > > installer {} is not available", installer_name);
+                continue;
+            }
+        };
+        let name = match &recorded {
+            Some((_, name)) => name.clone(),
+            None => package.name_for(installer_name),
+        };
+        let request = InstallRequest {
+            name: name.clone(),
+            version: package.version.clone(),
+            arguments: package.additional_arguments.clone(),
+        };
+        if is_change && recorded.is_none() && backend.is_installed(&request) != Some(true) {
+            debug!(
+                "> > > {} is not known to be installed by {}",
+                name, installer_name
+            );
+            continue;
+        }
+        if *action == Action::Install
+            && !options.dry_run
+            && backend.is_installed(&request) == Some(true)
+        {
+            info!("> > > {} is already installed by {}", name, installer_name);
+            ledger.record(package_name, package, installer_name, &name);
+            return ledger.save();
+        }
+        if options.dry_run {
+            info!(
+                "> > > SKIPPING `{}`",
+                backend.describe(action.verb(), &request)
+            );
+            return Ok(());
+        }
+        let result = match action {
+            Action::Install => backend.install(&request),
+            Action::Update => backend.update(&request),
+            Action::Delete => backend.delete(&request),
+        };
+        match result {
+            Ok(()) => {
+                match action {
+                    Action::Delete => {
+                        let _ = ledger.forget(package_name, package);
+                    }
+                    _ => ledger.record(package_name, package, installer_name, &name),
+                }
+                return ledger.save();
+            }
+            Err(err) if is_change => return Err(err),
+            Err(err) => warn!(
+                "installer {} failed, trying the next: {}",
+                installer_name, err
+            ),
+        }
+    }
+
+    match (action, &recorded) {
+        (Action::Delete, None) => {
+            warn!(
+                "{} is not known to be installed by any of {}, not deleting",
+                package.name, package.installer
+            );
+            Ok(())
+        }
+        (Action::Update, None) => Err(ErrorKind::InnerPackageNotInstalled(
+            package.name.clone(),
+            package.installer.to_string(),
+        )
+        .into()),
+        _ => {
+            error!(
+                "no installer could act on {}, candidates {:?}",
+                package.name, candidates
+            );
+            Err(ErrorKind::NoAvailableInstaller(
+                package.name.clone(),
+                package.installer.to_string(),
+            )
+            .into())
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[test]
+    fn test_run_script_fails() {
+        let temp = temp_dir();
+        std::fs::write(temp.path().join("good.sh"), "exit 0\n").unwrap();
+        std::fs::write(temp.path().join("bad.sh"), "echo 'no disk' >&2\nexit 3\n").unwrap();
+        let options = Options { dry_run: false };
+
+        assert!(run_script(temp.path(), Path::new("good.sh"), &options).is_ok());
+        let err = run_script(temp.path(), Path::new("bad.sh"), &options).unwrap_err();
+        assert!(err.to_string().ends_with("bad.sh failed: no disk"));
+        assert!(run_script(temp.path(), Path::new("bad.sh"), &Options { dry_run: true }).is_ok());
+    }
 }
@@ -7,18 +7,19 @@ More detailed description, with
 
 */
 
-use crate::config::current_configuration;
+use crate::config::Configuration;
 use crate::error::ErrorKind;
 use crate::error::Result;
 use crate::name::Name;
-use crate::package::install::Action;
+use crate::style::Styler;
 use crate::{Installable, Options};
-use serde::export::Formatter;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::{TryFrom, TryInto};
 use std::fmt::Display;
+use std::fmt::Formatter;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 // ------------------------------------------------------------------------------------------------
@@ -26,11 +27,26 @@ use std::str::FromStr;
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct Package {
     #[serde(skip)]
     pub(crate) path: PathBuf,
+    #[serde(skip)]
+    pub(crate) origins: BTreeMap<String, PathBuf>,
+    #[serde(skip)]
+    pub(crate) extends: Option<Name>,
     pub name: Name,
     pub layer: Name,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<Name>,
+    #[serde(default)]
+    pub homepage: Option<String>,
+    #[serde(default)]
+    pub maintainer: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<String>,
     pub packages: Vec<InnerPackage>,
     #[serde(default)]
     pub on_install: Option<ScriptSet>,
@@ -38,35 +54,49 @@ pub struct Package {
     pub on_update: Option<ScriptSet>,
     #[serde(default)]
     pub on_delete: Option<ScriptSet>,
-    #[serde(default)]
-    pub requires: Vec<String>,
 }
 
 #[derive(Clone, Debug, PartialOrd, Ord, PartialEq, Eq, Hash, Deserialize, Serialize)]
 #[repr(u8)]
+#[derive(Default)]
 pub enum InnerPackagePriority {
     Lowest,
     Lower,
+    #[default]
     Normal,
     Higher,
     Highest,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct InnerPackage {
     pub name: Name,
-    pub installer: Name,
-    #[serde(default)]
+    pub installer: InstallerChain,
+    #[serde(default, skip_serializing_if = "InnerPackagePriority::is_normal")]
     pub priority: InnerPackagePriority,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub is_app: bool,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub additional_arguments: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub platforms: Vec<Name>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub names: BTreeMap<Name, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
 }
 
+///
+/// The installers an inner package may be installed with, tried in order until one that is
+/// available on this machine succeeds. Written as either a single name or a non-empty list.
+///
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "OneOrMany", into = "OneOrMany")]
+pub struct InstallerChain(Vec<Name>);
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct ScriptSet {
     #[serde(default)]
     pub before: Option<PathBuf>,
@@ -74,88 +104,440 @@ pub struct ScriptSet {
     pub after: Option<PathBuf>,
 }
 
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(Name),
+    Many(Vec<Name>),
+}
+
 // ------------------------------------------------------------------------------------------------
 // Public Functions
 // ------------------------------------------------------------------------------------------------
 
+/// The environment variable naming the package root directory.
+pub const PACKPACK_HOME: &str = "PACKPACK_HOME";
+
+/// The original environment variable naming the package root, used if `PACKPACK_HOME` is not set.
 pub const DFPM_HOME: &str = "DFPM_HOME";
 
-pub fn get_package_dir() -> Result<PathBuf> {
-    let path = std::env::var(DFPM_HOME)?;
-    let path = PathBuf::from_str(&path)?;
-    if path.exists() && path.is_dir() {
-        Ok(path)
-    } else {
-        println!("{:?} {} {}", path, path.exists(), path.is_dir());
+pub const PACKAGE_FILE_NAME: &str = "package.toml";
+
+/// The key in an inner package's `names` for the name used by any other installer.
+pub const DEFAULT_NAME_KEY: &str = "default";
+
+/// The key in a package file naming the package it extends.
+pub(crate) const EXTENDS: &str = "extends";
+
+/// The installer name that refers to the current platform's system installer.
+pub(crate) const SYSTEM_INSTALLER: &str = "system";
+
+///
+/// The package roots to use when none are configured explicitly; this is the value of
+/// `PACKPACK_HOME`, or `DFPM_HOME`, or else `~/.packpack/pkgroot` as created by the bootstrap
+/// script. Either variable may hold a list of directories, separated as in `PATH`, in order of
+/// increasing precedence.
+///
+pub fn default_package_dirs() -> Vec<PathBuf> {
+    for var in &[PACKPACK_HOME, DFPM_HOME] {
+        if let Some(value) = std::env::var_os(var) {
+            let paths: Vec<PathBuf> = std::env::split_paths(&value)
+                .filter(|path| !path.as_os_str().is_empty())
+                .collect();
+            if !paths.is_empty() {
+                debug!("default_package_dirs() -> {:?} (from {})", paths, var);
+                return paths;
+            }
+        }
+    }
+    dirs::home_dir()
+        .map(|home| home.join(".packpack").join("pkgroot"))
+        .into_iter()
+        .collect()
+}
+
+///
+/// The package roots that exist, in order of increasing precedence; it is an error if none
+/// exist.
+///
+pub fn get_package_dirs(config: &Configuration) -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = Default::default();
+    for path in config.package_dirs() {
+        if path.is_dir() {
+            paths.push(path.clone());
+        } else {
+            warn!("package directory {:?} does not exist", path);
+        }
+    }
+    if paths.is_empty() {
         Err(crate::error::ErrorKind::MissingPackageDir.into())
+    } else {
+        Ok(paths)
     }
 }
 
-pub fn get_packages() -> Result<HashMap<Name, Package>> {
-    let parent_path = get_package_dir()?;
+///
+/// The existing package root with the highest precedence; new packages are created here, and
+/// layer scripts are relative to it.
+///
+pub fn get_package_dir(config: &Configuration) -> Result<PathBuf> {
+    Ok(get_package_dirs(config)?.pop().unwrap())
+}
+
+///
+/// All packages in all package roots; a package in a later root is merged over the one of the
+/// same name from earlier roots, see `Package::origin`.
+///
+pub fn get_packages(config: &Configuration) -> Result<HashMap<Name, Package>> {
     let mut packages: HashMap<Name, Package> = Default::default();
-    for entry in std::fs::read_dir(parent_path)? {
-        let entry = entry?;
-        let package_path = entry.path();
-        if package_path.is_dir() {
-            let package_file = package_path.join("package.toml");
-            if package_file.exists() && package_file.is_file() {
-                let package = Package::load_from(&package_file)?;
-                packages.insert(package.name.clone(), package);
+    for parent_path in get_package_dirs(config)? {
+        for package_file in package_files(&parent_path)? {
+            let package = Package::load_from(config, &package_file)?;
+            match packages.get_mut(&package.name) {
+                Some(existing) => {
+                    let origin = package.path.clone();
+                    existing.apply_overlay(package.into(), Some(&origin))
+                }
+                None => {
+                    let _ = packages.insert(package.name.clone(), package);
+                }
             }
         }
     }
     Ok(packages)
 }
 
+///
+/// Create a new package directory, named `name`, in the package root with a starter
+/// `package.toml`. The layer must be an inner layer, and if `installer` is provided a single
+/// inner package of the same name is added for it. If `with_scripts` is `true` stub before and
+/// after install scripts are written that source the bootstrap `logging.sh`.
+///
+pub fn create_package(
+    config: &Configuration,
+    name: &Name,
+    layer: &Name,
+    installer: Option<&Name>,
+    with_scripts: bool,
+) -> Result<Package> {
+    create::create_package(config, name, layer, installer, with_scripts)
+}
+
+///
+/// Add `inner` to the named package, editing its `package.toml` in place so that existing
+/// formatting and comments are kept. The installer must exist, and the package must not already
+/// contain an inner package with the same name and installer.
+///
+pub fn add_inner_package(
+    config: &Configuration,
+    package: &Name,
+    inner: &InnerPackage,
+) -> Result<Package> {
+    edit::add_inner_package(config, package, inner)
+}
+
+///
+/// Remove the inner package named `inner` from the named package, or only the one for
+/// `installer` if it is provided, editing its `package.toml` in place.
+///
+pub fn remove_inner_package(
+    config: &Configuration,
+    package: &Name,
+    inner: &Name,
+    installer: Option<&Name>,
+) -> Result<Package> {
+    edit::remove_inner_package(config, package, inner, installer)
+}
+
+///
+/// Check that `remove_inner_package` would find the inner package to remove; that is, that the
+/// named package's own file, not one it extends or an overlay, contains it.
+///
+pub fn check_inner_package_removable(
+    config: &Configuration,
+    package: &Name,
+    inner: &Name,
+    installer: Option<&Name>,
+) -> Result<()> {
+    edit::own_inner_packages(config, package, inner, installer).map(|_| ())
+}
+
+///
+/// Check every package in the package directory against the provided configuration, returning
+/// all problems found rather than stopping at the first.
+///
+pub fn validate_packages(config: &Configuration) -> Result<Vec<Problem>> {
+    validate::validate_packages(config)
+}
+
 // ------------------------------------------------------------------------------------------------
 // Implementations
 // ------------------------------------------------------------------------------------------------
 
 impl Installable for Package {
-    fn install(&self, options: &Options) -> Result<()> {
+    fn install(&self, config: &Configuration, options: &Options) -> Result<()> {
         info!("Package::install({:?})", options);
-        install::install_action(Action::Install, self, options)
+        let mut session = Session::for_config(config)?;
+        install_action(Action::Install, self, config, &mut session, options)
     }
 
-    fn update(&self, options: &Options) -> Result<()> {
+    fn update(&self, config: &Configuration, options: &Options) -> Result<()> {
         info!("Package::update({:?})", options);
-        install::install_action(Action::Update, self, options)
+        let mut session = Session::for_config(config)?;
+        install_action(Action::Update, self, config, &mut session, options)
     }
 
-    fn delete(&self, options: &Options) -> Result<()> {
+    fn delete(&self, config: &Configuration, options: &Options) -> Result<()> {
         info!("Package::delete({:?})", options);
-        install::install_action(Action::Delete, self, options)
+        let mut session = Session::for_config(config)?;
+        install_action(Action::Delete, self, config, &mut session, options)
+    }
+}
+
+impl InnerPackage {
+    /// The key for this inner package's entry in `Package::origin`.
+    pub fn origin_key(&self) -> String {
+        format!("packages.{}.{}", self.name, self.installer.key())
+    }
+
+    ///
+    /// The configured installers, in order, that may install this package on the current
+    /// platform; `system` is replaced by the platform's system installer, or for an app by its
+    /// app installer, if it has one.
+    ///
+    pub fn candidate_installers<'a>(&'a self, config: &'a Configuration) -> Vec<&'a Name> {
+        let platform = config.platform();
+        let system_installer = if self.is_app {
+            &platform.app_installer
+        } else {
+            &platform.system_installer
+        };
+        let mut candidates: Vec<&Name> = Default::default();
+        for installer in self.installer.iter() {
+            let installer = if installer.to_string().eq_ignore_ascii_case(SYSTEM_INSTALLER) {
+                match system_installer {
+                    Some(installer) => installer,
+                    None => continue,
+                }
+            } else {
+                installer
+            };
+            let supported = config
+                .installer(installer)
+                .map(|i| i.supports(&config.platform().target_os))
+                .unwrap_or_default();
+            if supported && !candidates.contains(&installer) {
+                candidates.push(installer);
+            }
+        }
+        candidates
+    }
+
+    ///
+    /// The installer expected to install this package; the first candidate that is available on
+    /// this machine, or else the first candidate.
+    ///
+    pub fn resolved_installer<'a>(&'a self, config: &'a Configuration) -> Option<&'a Name> {
+        let candidates = self.candidate_installers(config);
+        candidates
+            .iter()
+            .find(|name| {
+                config
+                    .installer(name)
+                    .map(|installer| installer.is_available())
+                    .unwrap_or_default()
+            })
+            .or_else(|| candidates.first())
+            .copied()
+    }
+
+    ///
+    /// The name to pass to the resolved installer; the entry in `names` for that installer, else
+    /// the `default` entry, else the inner package's own name.
+    ///
+    pub fn installed_name(&self, config: &Configuration) -> String {
+        match self.resolved_installer(config) {
+            Some(installer) => self.name_for(installer),
+            None => self
+                .names
+                .get(&default_name())
+                .cloned()
+                .unwrap_or_else(|| self.name.to_string()),
+        }
+    }
+
+    /// The name to pass to `installer`, as for `installed_name`.
+    pub fn name_for(&self, installer: &Name) -> String {
+        self.names
+            .get(installer)
+            .or_else(|| self.names.get(&default_name()))
+            .cloned()
+            .unwrap_or_else(|| self.name.to_string())
+    }
+}
+
+impl Display for InstallerChain {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.0
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+    }
+}
+
+impl From<Name> for InstallerChain {
+    fn from(name: Name) -> Self {
+        Self(vec![name])
+    }
+}
+
+impl TryFrom<Vec<Name>> for InstallerChain {
+    type Error = String;
+
+    fn try_from(names: Vec<Name>) -> std::result::Result<Self, Self::Error> {
+        if names.is_empty() {
+            Err("expected at least one installer".to_string())
+        } else {
+            Ok(Self(names))
+        }
+    }
+}
+
+impl TryFrom<OneOrMany> for InstallerChain {
+    type Error = String;
+
+    fn try_from(value: OneOrMany) -> std::result::Result<Self, Self::Error> {
+        match value {
+            OneOrMany::One(name) => Ok(name.into()),
+            OneOrMany::Many(names) => names.try_into(),
+        }
+    }
+}
+
+impl From<InstallerChain> for OneOrMany {
+    fn from(chain: InstallerChain) -> Self {
+        let mut names = chain.0;
+        if names.len() == 1 {
+            OneOrMany::One(names.remove(0))
+        } else {
+            OneOrMany::Many(names)
+        }
+    }
+}
+
+impl InstallerChain {
+    /// The first, preferred, installer.
+    pub fn first(&self) -> &Name {
+        &self.0[0]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Name> {
+        self.0.iter()
+    }
+
+    pub fn contains(&self, installer: &Name) -> bool {
+        self.0.contains(installer)
+    }
+
+    /// The installers separated by `,`, for use in keys such as those of `Package::origin`.
+    pub fn key(&self) -> String {
+        self.0
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl InnerPackagePriority {
+    fn is_normal(&self) -> bool {
+        *self == InnerPackagePriority::Normal
     }
 }
 
 impl Package {
-    pub fn load(name: &Name) -> Result<Self> {
+    pub fn load(config: &Configuration, name: &Name) -> Result<Self> {
         info!("Package::load({})", name);
-        let mut path = get_package_dir()?;
-        path.push(&name.to_string());
-        path.push("package.toml");
-        if path.exists() && path.is_file() {
+        Self::load_extended(config, name, &[])
+    }
+
+    fn load_from(config: &Configuration, path: &Path) -> Result<Self> {
+        Self::load_file(config, path, &[])
+    }
+
+    ///
+    /// Load the named package, merging the files from every package root that defines it.
+    /// `extending` is the chain of packages that extend this one, used to detect cycles.
+    ///
+    fn load_extended(config: &Configuration, name: &Name, extending: &[Name]) -> Result<Self> {
+        let mut package: Option<Package> = None;
+        for path in find_package_files(config, name)? {
             debug!(".. loading from file {:?}", path);
-            Self::load_from(&path)
-        } else {
-            info!(".. package file {:?} does not exist", path);
-            Err(crate::error::ErrorKind::NoSuchPackage(name.clone()).into())
+            let loaded = Self::load_file(config, &path, extending)?;
+            match &mut package {
+                Some(package) => {
+                    let origin = loaded.path.clone();
+                    package.apply_overlay(loaded.into(), Some(&origin))
+                }
+                None => package = Some(loaded),
+            }
         }
+        Ok(package.unwrap())
     }
 
-    fn load_from(path: &PathBuf) -> Result<Self> {
-        let content = std::fs::read_to_string(path.clone())?;
-        let mut package: Package = toml::from_str(&content)?;
+    fn load_file(config: &Configuration, path: &Path, extending: &[Name]) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let is_extension = match toml::from_str::<toml::Value>(&content)? {
+            toml::Value::Table(table) => table.contains_key(EXTENDS),
+            _ => false,
+        };
+
+        let mut package = if is_extension {
+            let overlay: PackageOverlay = toml::from_str(&content)?;
+            let name = overlay.name.clone().ok_or_else(|| {
+                ErrorKind::InvalidConfigFile(
+                    path.to_path_buf(),
+                    None,
+                    "missing field `name`".to_string(),
+                )
+            })?;
+            let base_name = overlay.extends.clone().unwrap();
+            if base_name == name || extending.contains(&name) {
+                error!("package {} extends itself", name);
+                return Err(ErrorKind::InvalidPackageExtends(name).into());
+            }
+            let mut extending = extending.to_vec();
+            extending.push(name.clone());
+            let mut package = Self::load_extended(config, &base_name, &extending)?;
+            package.resolve_scripts();
+            package.name = name;
+            package.path = path.to_path_buf();
+            package.extends = Some(base_name);
+            package.apply_overlay(overlay, None);
+            package
+        } else {
+            let mut package: Package = toml::from_str(&content)?;
+            package.path = path.to_path_buf();
+            package
+        };
 
-        package.path = path.clone();
+        for overlay_file in overlay::overlay_files(config, path) {
+            debug!(".. applying overlay file {:?}", overlay_file);
+            package.apply_overlay(PackageOverlay::load(&overlay_file)?, Some(&overlay_file));
+        }
 
-        let config = current_configuration().as_ref().unwrap();
-        let layer = config
-            .layers()
-            .filter_map(|layer| layer.find(&package.layer))
-            .next();
-        match layer {
+        match config.find_layer(&package.layer) {
             Some(layer) => {
                 if layer.is_inner() {
                     Ok(package)
@@ -171,20 +553,62 @@ impl Package {
         }
     }
 
-    pub fn inspect(&self, w: &mut impl Write, colored: bool) -> Result<()> {
-        info!("Package::inspect(..., {})", colored);
-        inspect::inspect_package(self, w, colored)
+    /// The path of the file this package was loaded from, or created in.
+    pub fn path(&self) -> &Path {
+        &self.path
     }
-}
 
-// ------------------------------------------------------------------------------------------------
+    /// The package this one extends, if any.
+    pub fn extends(&self) -> Option<&Name> {
+        self.extends.as_ref()
+    }
 
-impl Default for InnerPackagePriority {
-    fn default() -> Self {
-        Self::Normal
+    /// The files merged over the one this package was loaded from; those from later package
+    /// roots, and overlay files.
+    pub fn overrides(&self) -> Vec<&Path> {
+        let mut overrides: Vec<&Path> = Default::default();
+        for path in self.origins.values() {
+            if !overrides.contains(&path.as_path()) {
+                overrides.push(path);
+            }
+        }
+        overrides.sort();
+        overrides
+    }
+
+    ///
+    /// The override, or overlay, file that last set the value at `path`, such as `description`
+    /// or `packages.nu.cargo` for an inner package, or `None` if the value is from this package's
+    /// own file or the package it extends.
+    ///
+    pub fn origin(&self, path: &str) -> Option<&Path> {
+        self.origins.get(path).map(|path| path.as_path())
+    }
+
+    ///
+    /// Returns `true` if `text` appears, without regard to case, in this package's name,
+    /// description, tags, or the name of any of its inner packages.
+    ///
+    pub fn matches_text(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        let contains = |s: &str| s.to_lowercase().contains(&text);
+        contains(&self.name.to_string())
+            || self.description.as_deref().map(contains).unwrap_or(false)
+            || self.tags.iter().any(|tag| contains(&tag.to_string()))
+            || self
+                .packages
+                .iter()
+                .any(|inner| contains(&inner.name.to_string()))
+    }
+
+    pub fn inspect(&self, w: &mut impl Write, styler: &Styler) -> Result<()> {
+        info!("Package::inspect(..., {:?})", styler);
+        inspect::inspect_package(self, w, styler)
     }
 }
 
+// ------------------------------------------------------------------------------------------------
+
 impl Display for InnerPackagePriority {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -201,13 +625,90 @@ impl Display for InnerPackagePriority {
     }
 }
 
+impl FromStr for InnerPackagePriority {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "highest" => Ok(InnerPackagePriority::Highest),
+            "higher" => Ok(InnerPackagePriority::Higher),
+            "normal" => Ok(InnerPackagePriority::Normal),
+            "lower" => Ok(InnerPackagePriority::Lower),
+            "lowest" => Ok(InnerPackagePriority::Lowest),
+            _ => Err(format!("invalid value '{}' for priority", s)),
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The package files that define the named package, in order of increasing precedence; those
+/// whose `name` matches, whatever directory they are in, as for `get_packages`.
+///
+fn find_package_files(config: &Configuration, name: &Name) -> Result<Vec<PathBuf>> {
+    let package_dirs = get_package_dirs(config)?;
+    let mut found: Vec<PathBuf> = Default::default();
+    let mut names: Vec<Name> = Default::default();
+    for parent_path in &package_dirs {
+        for package_file in package_files(parent_path)? {
+            // Only the name is read, so that a broken package does not hide every other.
+            let content = std::fs::read_to_string(&package_file)?;
+            let file_name = toml::from_str::<toml::Value>(&content)
+                .ok()
+                .and_then(|value| value.get("name").and_then(|v| v.as_str()).map(String::from))
+                .and_then(|file_name| file_name.parse::<Name>().ok());
+            match file_name {
+                Some(file_name) if &file_name == name => found.push(package_file),
+                Some(file_name) => names.push(file_name),
+                None => {}
+            }
+        }
+    }
+    if found.is_empty() {
+        Err(ErrorKind::NoSuchPackage(name.clone(), name.suggestions(names.iter())).into())
+    } else {
+        Ok(found)
+    }
+}
+
+fn default_name() -> Name {
+    Name::from_str(DEFAULT_NAME_KEY).unwrap()
+}
+
+/// The package files directly within the package directories of `parent_path`, sorted.
+pub(crate) fn package_files(parent_path: &Path) -> Result<Vec<PathBuf>> {
+    let mut package_files: Vec<PathBuf> = Default::default();
+    for entry in std::fs::read_dir(parent_path)? {
+        let package_file = entry?.path().join(PACKAGE_FILE_NAME);
+        if package_file.is_file() {
+            package_files.push(package_file);
+        }
+    }
+    package_files.sort();
+    Ok(package_files)
+}
+
 // ------------------------------------------------------------------------------------------------
 // Modules
 // ------------------------------------------------------------------------------------------------
 
+mod create;
+
+mod edit;
+
 mod inspect;
 
+mod overlay;
+pub(crate) use overlay::PackageOverlay;
+
 mod install;
+pub(crate) use install::{install_action, layer_action, Action, Session};
+
+mod validate;
+pub use validate::Problem;
 
 // ------------------------------------------------------------------------------------------------
 // Unit Tests
@@ -216,30 +717,32 @@ mod install;
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::ConfigurationBuilder;
+    use crate::testing::{temp_dir, test_configuration, test_package_root, TestPackageRoot};
     #[allow(unused_imports)]
     use pretty_assertions::{assert_eq, assert_ne};
-
-    fn set_test_package_root() -> String {
-        let pkgs_dir = format!("{}/test-pkgs", env!("CARGO_MANIFEST_DIR"));
-        std::env::set_var(DFPM_HOME, pkgs_dir.clone());
-        pkgs_dir
-    }
+    use std::str::FromStr;
 
     #[test]
     fn test_get_package_dir() {
-        let pkgs_dir = set_test_package_root();
-
-        let package_dir = get_package_dir();
+        let package_dir = get_package_dir(&test_configuration());
         println!("{:#?}", package_dir);
         assert!(package_dir.is_ok());
-        assert_eq!(package_dir.unwrap(), PathBuf::from_str(&pkgs_dir).unwrap())
+        assert_eq!(package_dir.unwrap(), test_package_root())
     }
 
     #[test]
-    fn test_get_packages() {
-        let _ = set_test_package_root();
+    fn test_missing_package_dir() {
+        let config = ConfigurationBuilder::new()
+            .package_dir(&test_package_root().join("no-such-dir"))
+            .build()
+            .unwrap();
+        assert!(get_package_dir(&config).is_err());
+    }
 
-        let pkgs = get_packages();
+    #[test]
+    fn test_get_packages() {
+        let pkgs = get_packages(&test_configuration());
         println!("{:#?}", pkgs);
         assert!(pkgs.is_ok());
         let pkgs = pkgs.unwrap();
@@ -250,17 +753,23 @@ mod tests {
 
     #[test]
     fn test_load_package() {
-        let _ = set_test_package_root();
-
-        let result = Package::load(&"Rust".parse().unwrap());
+        let result = Package::load(&test_configuration(), &"Rust".parse().unwrap());
         assert!(result.is_ok());
         let package = result.unwrap();
         println!("**********");
-        let result = package.inspect(&mut std::io::stdout(), true);
+        let result = package.inspect(&mut std::io::stdout(), &Styler::new(true));
         println!("**********");
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_validate_packages() {
+        let problems = validate_packages(&test_configuration());
+        println!("{:#?}", problems);
+        assert!(problems.is_ok());
+        assert!(problems.unwrap().is_empty());
+    }
+
     #[test]
     fn test_priority_order() {
         assert!(InnerPackagePriority::Highest > InnerPackagePriority::Higher);
@@ -268,4 +777,392 @@ mod tests {
         assert!(InnerPackagePriority::Normal > InnerPackagePriority::Lower);
         assert!(InnerPackagePriority::Lower > InnerPackagePriority::Lowest);
     }
+
+    #[test]
+    fn test_matches_text() {
+        let package = Package::load(&test_configuration(), &"rust".parse().unwrap()).unwrap();
+        assert!(package.matches_text("RUST"));
+        assert!(package.matches_text("toolchain"));
+        assert!(package.matches_text("cli"));
+        assert!(package.matches_text("skim"));
+        assert!(!package.matches_text("python"));
+    }
+
+    #[test]
+    fn test_create_package() {
+        let package_root = TestPackageRoot::new();
+        let config = package_root.configuration();
+        let name: Name = "Fish".parse().unwrap();
+
+        let result = create_package(&config, &name, &"system".parse().unwrap(), None, false);
+        assert!(result.is_err());
+
+        let package = create_package(
+            &config,
+            &name,
+            &"shell".parse().unwrap(),
+            Some(&"cargo".parse().unwrap()),
+            true,
+        )
+        .unwrap();
+        assert!(package.path().is_file());
+        assert!(package_root.path().join("Fish/before-install.sh").is_file());
+
+        let loaded = Package::load(&config, &name).unwrap();
+        assert_eq!(loaded.layer, Name::from_str("shell").unwrap());
+        assert_eq!(loaded.packages.len(), 1);
+        assert!(loaded.on_install.is_some());
+        assert!(validate_packages(&config).unwrap().is_empty());
+
+        let result = create_package(&config, &name, &"shell".parse().unwrap(), None, false);
+        assert!(result.is_err());
+
+        let _ = package_root.write_package(
+            "Other",
+            r#"name = "FISH"
+layer = "shell"
+packages = [
+    { name = "fd", installer = "cargo" },
+    { name = "FD", installer = "Cargo" },
+]
+"#,
+        );
+        let messages: Vec<String> = validate_packages(&config)
+            .unwrap()
+            .into_iter()
+            .map(|problem| problem.message)
+            .collect();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].starts_with("package 'FISH' is also defined in"));
+        assert_eq!(
+            messages[1],
+            "inner package 'FD' is listed more than once for installer 'Cargo'"
+        );
+    }
+
+    #[test]
+    fn test_inner_package_names() {
+        let inner: InnerPackage = toml::from_str(
+            r#"name = "fd"
+installer = "system"
+names = { apt = "fd-find", Homebrew = "fd-brew", default = "fd-default" }
+"#,
+        )
+        .unwrap();
+        let macos = ConfigurationBuilder::new()
+            .package_dir(&test_package_root())
+            .target_os("macos".parse().unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(
+            inner.resolved_installer(&macos),
+            Some(&"homebrew".parse().unwrap())
+        );
+        assert_eq!(inner.installed_name(&macos), "fd-brew");
+
+        let windows = ConfigurationBuilder::new()
+            .package_dir(&test_package_root())
+            .target_os("windows".parse().unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(inner.installed_name(&windows), "fd-default");
+
+        let linux = ConfigurationBuilder::new()
+            .package_dir(&test_package_root())
+            .target_os("linux".parse().unwrap())
+            .build()
+            .unwrap();
+        let chain: InnerPackage = toml::from_str(
+            r#"name = "fd"
+installer = ["system", "apt", "cargo"]
+names = { apt = "fd-find" }
+"#,
+        )
+        .unwrap();
+        assert_eq!(chain.installer.to_string(), "system, apt, cargo");
+        let names = |installers: Vec<&Name>| -> Vec<String> {
+            installers.iter().map(|name| name.to_string()).collect()
+        };
+        assert_eq!(
+            names(chain.candidate_installers(&linux)),
+            vec!["apt", "cargo"]
+        );
+        assert_eq!(
+            names(chain.candidate_installers(&macos)),
+            vec!["homebrew", "cargo"]
+        );
+        assert_eq!(
+            names(chain.candidate_installers(&windows)),
+            vec!["scoop", "cargo"]
+        );
+
+        let app: InnerPackage = toml::from_str(
+            r#"name = "firefox"
+installer = "system"
+is_app = true
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            names(app.candidate_installers(&macos)),
+            vec!["homebrew-apps"]
+        );
+        assert!(app.candidate_installers(&windows).is_empty());
+        assert!(toml::to_string(&chain)
+            .unwrap()
+            .contains("installer = [\"system\", \"apt\", \"cargo\"]"));
+        assert!(toml::to_string(&inner)
+            .unwrap()
+            .contains("installer = \"system\""));
+        assert!(toml::from_str::<InnerPackage>("name = \"fd\"\ninstaller = []\n").is_err());
+
+        let unnamed = InnerPackage {
+            names: Default::default(),
+            ..inner
+        };
+        assert_eq!(unnamed.installed_name(&macos), "fd");
+    }
+
+    #[test]
+    fn test_add_remove_inner_package() {
+        let package_root = TestPackageRoot::new();
+        let original =
+            std::fs::read_to_string(test_package_root().join("Rust").join(PACKAGE_FILE_NAME))
+                .unwrap();
+        let package_file = package_root.write_package("Rust", &format!("# comment\n{}", original));
+        let config = package_root.configuration();
+        let rust: Name = "rust".parse().unwrap();
+        let inner = InnerPackage {
+            name: "ripgrep".parse().unwrap(),
+            installer: Name::from_str("cargo").unwrap().into(),
+            priority: InnerPackagePriority::Higher,
+            is_app: false,
+            additional_arguments: vec!["--locked".to_string()],
+            platforms: Default::default(),
+            names: Default::default(),
+            version: None,
+        };
+
+        let package = add_inner_package(&config, &rust, &inner).unwrap();
+        assert_eq!(package.packages.len(), 27);
+        let added = package.packages.last().unwrap();
+        assert_eq!(added.name, inner.name);
+        assert_eq!(added.priority, InnerPackagePriority::Higher);
+        assert!(add_inner_package(&config, &rust, &inner).is_err());
+
+        let package = remove_inner_package(&config, &rust, &inner.name, None).unwrap();
+        assert_eq!(package.packages.len(), 26);
+        assert!(remove_inner_package(&config, &rust, &inner.name, None).is_err());
+        assert_eq!(
+            std::fs::read_to_string(&package_file).unwrap(),
+            format!("# comment\n{}", original)
+        );
+    }
+
+    #[test]
+    fn test_package_root_overrides() {
+        let personal_root = TestPackageRoot::new();
+        // The directory name differs, only the name in the file makes this an override.
+        let override_file = personal_root.write_package(
+            "Personal-Rust",
+            r#"name = "rust"
+layer = "programming-languages"
+description = "Personal"
+tags = ["personal"]
+packages = [
+    { name = "ripgrep", installer = "cargo" },
+    { name = "xsv", installer = "cargo", additional_arguments = ["--locked"] },
+]
+"#,
+        );
+        let config = personal_root.builder_over_examples().build().unwrap();
+        assert_eq!(config.package_dirs().len(), 2);
+        assert_eq!(get_package_dir(&config).unwrap(), personal_root.path());
+
+        let package = Package::load(&config, &"Rust".parse().unwrap()).unwrap();
+        assert_eq!(
+            package.path(),
+            test_package_root().join("Rust").join(PACKAGE_FILE_NAME)
+        );
+        assert_eq!(package.overrides(), vec![override_file.as_path()]);
+        assert_eq!(package.description, Some("Personal".to_string()));
+        assert_eq!(package.origin("description"), Some(override_file.as_path()));
+        assert_eq!(package.origin("homepage"), None);
+        assert_eq!(package.tags.len(), 3);
+        assert_eq!(package.packages.len(), 27);
+        let xsv = package
+            .packages
+            .iter()
+            .find(|inner| inner.name == Name::from_str("xsv").unwrap())
+            .unwrap();
+        assert_eq!(xsv.additional_arguments, vec!["--locked".to_string()]);
+        assert_eq!(
+            package.origin(&xsv.origin_key()),
+            Some(override_file.as_path())
+        );
+
+        let packages = get_packages(&config).unwrap();
+        assert_eq!(packages.len(), 1);
+        assert!(validate_packages(&config).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_package_extends_and_overlays() {
+        let personal_root = TestPackageRoot::new();
+        let package_file = personal_root.write_package(
+            "MyRust",
+            r#"name = "my-rust"
+extends = "rust"
+description = "Rust, my way"
+remove_packages = ["rls"]
+packages = [{ name = "tokei", installer = "cargo", names = { cargo = "tokei", default = "tokei-cli" } }]
+"#,
+        );
+        let host_file = personal_root
+            .path()
+            .join("MyRust")
+            .join("package.testhost.toml");
+        std::fs::write(
+            &host_file,
+            r#"remove_packages = ["tokei"]
+remove_scripts = ["on_install"]
+tags = ["work"]
+"#,
+        )
+        .unwrap();
+        let config = personal_root
+            .builder_over_examples()
+            .hostname("TestHost")
+            .build()
+            .unwrap();
+
+        let package = Package::load(&config, &"my-rust".parse().unwrap()).unwrap();
+        assert_eq!(package.path(), package_file);
+        assert_eq!(package.extends(), Some(&"Rust".parse().unwrap()));
+        assert_eq!(package.layer, "programming-languages".parse().unwrap());
+        assert_eq!(package.description, Some("Rust, my way".to_string()));
+        assert_eq!(package.origin("description"), None);
+        assert_eq!(package.tags.len(), 3);
+        assert_eq!(package.origin("tags"), Some(host_file.as_path()));
+        assert_eq!(package.packages.len(), 25);
+        assert!(package.on_install.is_none());
+        assert!(validate_packages(&config).unwrap().is_empty());
+
+        std::fs::remove_file(&host_file).unwrap();
+        let package = Package::load(&config, &"my-rust".parse().unwrap()).unwrap();
+        assert_eq!(package.packages.len(), 26);
+        assert_eq!(
+            package.on_install.unwrap().before,
+            Some(test_package_root().join("Rust").join("install-rustup"))
+        );
+        assert_eq!(get_packages(&config).unwrap().len(), 2);
+
+        let _ = personal_root.write_package(
+            "MyRust",
+            r#"name = "my-rust"
+extends = "my-rust"
+"#,
+        );
+        assert!(Package::load(&config, &"my-rust".parse().unwrap()).is_err());
+        assert_eq!(validate_packages(&config).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_installer_fallback() {
+        let package_root = TestPackageRoot::new();
+        let _ = package_root.write_package(
+            "Tools",
+            r#"name = "tools"
+layer = "programming-languages"
+
+[[packages]]
+name = "fd"
+installer = ["missing", "broken", "works"]
+names = { works = "fd-find" }
+"#,
+        );
+        let temp = temp_dir();
+        let state_file = temp.path().join("state.toml");
+        let config = package_root
+            .builder()
+            .state_file(&state_file)
+            .installers_from_str(
+                r#"[missing]
+name = "Missing"
+command = "packpack-no-such-command"
+
+[broken]
+name = "Broken"
+command = "false"
+install_arguments = ["{}"]
+delete_arguments = ["{}"]
+check_arguments = ["{}"]
+
+[works]
+name = "Works"
+command = "true"
+install_arguments = ["{}"]
+delete_arguments = ["{}"]
+check_arguments = ["{}"]
+"#,
+            )
+            .build()
+            .unwrap();
+
+        let package = Package::load(&config, &"tools".parse().unwrap()).unwrap();
+        package
+            .install(&config, &Options { dry_run: true })
+            .unwrap();
+        assert!(!state_file.exists());
+
+        package
+            .install(&config, &Options { dry_run: false })
+            .unwrap();
+        let ledger = crate::state::Ledger::load(&state_file).unwrap();
+        let entry = ledger.find(&package.name, &package.packages[0]).unwrap();
+        assert_eq!(entry.installer.to_string(), "works");
+        assert_eq!(entry.name, "fd-find");
+
+        let mut ledger = crate::state::Ledger::load(&state_file).unwrap();
+        let broken: Name = "broken".parse().unwrap();
+        ledger.record(&package.name, &package.packages[0], &broken, "fd");
+        ledger.save().unwrap();
+        assert!(package
+            .delete(&config, &Options { dry_run: false })
+            .is_err());
+        let mut ledger = crate::state::Ledger::load(&state_file).unwrap();
+        let entry = ledger.find(&package.name, &package.packages[0]).unwrap();
+        assert_eq!(entry.installer, broken);
+
+        let works: Name = "works".parse().unwrap();
+        ledger.record(&package.name, &package.packages[0], &works, "fd-find");
+        ledger.save().unwrap();
+        package
+            .delete(&config, &Options { dry_run: false })
+            .unwrap();
+        let ledger = crate::state::Ledger::load(&state_file).unwrap();
+        assert_eq!(ledger.entries().count(), 0);
+
+        // with no record, only an installer reporting the package as installed may act on it.
+        package
+            .delete(&config, &Options { dry_run: false })
+            .unwrap();
+        assert!(package
+            .update(&config, &Options { dry_run: false })
+            .unwrap_err()
+            .to_string()
+            .contains("does not support update"));
+        let broken_only: InnerPackage =
+            toml::from_str("name = \"fd\"\ninstaller = \"broken\"\n").unwrap();
+        let package = Package {
+            packages: vec![broken_only],
+            ..package
+        };
+        package
+            .delete(&config, &Options { dry_run: false })
+            .unwrap();
+        assert!(package
+            .update(&config, &Options { dry_run: false })
+            .is_err());
+    }
 }
@@ -0,0 +1,258 @@
+use crate::config::Configuration;
+use crate::error::Result;
+use crate::name::Name;
+use crate::package::{InnerPackage, Package, ScriptSet, PACKAGE_FILE_NAME};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The content of a package file that `extends` another package, of an overlay file, or of a
+/// package in a later package root; every value is optional and only those present change the
+/// package it is applied to.
+///
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PackageOverlay {
+    #[serde(default)]
+    pub(crate) name: Option<Name>,
+    #[serde(default)]
+    pub(crate) extends: Option<Name>,
+    #[serde(default)]
+    layer: Option<Name>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    tags: Vec<Name>,
+    #[serde(default)]
+    homepage: Option<String>,
+    #[serde(default)]
+    maintainer: Option<String>,
+    #[serde(default)]
+    requires: Vec<String>,
+    #[serde(default)]
+    pub(crate) packages: Vec<InnerPackage>,
+    #[serde(default)]
+    remove_packages: Vec<Name>,
+    #[serde(default)]
+    on_install: Option<ScriptSet>,
+    #[serde(default)]
+    on_update: Option<ScriptSet>,
+    #[serde(default)]
+    on_delete: Option<ScriptSet>,
+    #[serde(default)]
+    remove_scripts: Vec<ScriptSetKind>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+enum ScriptSetKind {
+    #[serde(rename = "on_install")]
+    Install,
+    #[serde(rename = "on_update")]
+    Update,
+    #[serde(rename = "on_delete")]
+    Delete,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The overlay files that apply to `package_file` on this machine, in the order they are
+/// applied; `package.<target_os>.toml` and then `package.<hostname>.toml`.
+///
+pub(super) fn overlay_files(config: &Configuration, package_file: &Path) -> Vec<PathBuf> {
+    let parent = package_file.parent().unwrap_or_else(|| Path::new(""));
+    let stem = Path::new(PACKAGE_FILE_NAME)
+        .file_stem()
+        .unwrap()
+        .to_string_lossy()
+        .to_string();
+    let mut suffixes = vec![config.platform().target_os.to_string().to_lowercase()];
+    if let Some(hostname) = config.hostname() {
+        suffixes.push(hostname.to_string());
+    }
+    suffixes
+        .into_iter()
+        .map(|suffix| parent.join(format!("{}.{}.toml", stem, suffix)))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl From<Package> for PackageOverlay {
+    fn from(package: Package) -> Self {
+        Self {
+            name: Some(package.name),
+            extends: None,
+            layer: Some(package.layer),
+            description: package.description,
+            tags: package.tags,
+            homepage: package.homepage,
+            maintainer: package.maintainer,
+            requires: package.requires,
+            packages: package.packages,
+            remove_packages: Default::default(),
+            on_install: package.on_install,
+            on_update: package.on_update,
+            on_delete: package.on_delete,
+            remove_scripts: Default::default(),
+        }
+    }
+}
+
+impl PackageOverlay {
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
+}
+
+impl Package {
+    ///
+    /// Apply `overlay`, read from the file `origin`, over this package. Any values it sets
+    /// replace this package's, as do inner packages with the same name and installer; tags,
+    /// required commands, and other inner packages are added. Inner packages, and script sets,
+    /// may also be removed.
+    ///
+    /// An `origin` of `None` is the package's own file, for a package that extends another.
+    ///
+    pub(super) fn apply_overlay(&mut self, overlay: PackageOverlay, origin: Option<&Path>) {
+        debug!("applying {:?} over {:?}", origin, self.path);
+        // Scripts are relative to the package file that names them.
+        let base_path = origin
+            .unwrap_or(&self.path)
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .to_path_buf();
+        let mut changed: Vec<String> = Default::default();
+
+        if let Some(layer) = overlay.layer {
+            if layer != self.layer {
+                self.layer = layer;
+                changed.push("layer".to_string());
+            }
+        }
+        for (key, value, from) in IntoIterator::into_iter([
+            ("description", &mut self.description, overlay.description),
+            ("homepage", &mut self.homepage, overlay.homepage),
+            ("maintainer", &mut self.maintainer, overlay.maintainer),
+        ]) {
+            if from.is_some() {
+                *value = from;
+                changed.push(key.to_string());
+            }
+        }
+        for tag in overlay.tags {
+            if !self.tags.contains(&tag) {
+                self.tags.push(tag);
+                changed.push("tags".to_string());
+            }
+        }
+        for command in overlay.requires {
+            if !self.requires.contains(&command) {
+                self.requires.push(command);
+                changed.push("requires".to_string());
+            }
+        }
+
+        for kind in overlay.remove_scripts {
+            let (key, value) = self.script_set_mut(kind);
+            *value = None;
+            changed.push(key.to_string());
+        }
+        for (kind, from) in IntoIterator::into_iter([
+            (ScriptSetKind::Install, overlay.on_install),
+            (ScriptSetKind::Update, overlay.on_update),
+            (ScriptSetKind::Delete, overlay.on_delete),
+        ]) {
+            if let Some(from) = from {
+                let (key, value) = self.script_set_mut(kind);
+                let script_set = value.get_or_insert(ScriptSet {
+                    before: None,
+                    after: None,
+                });
+                if let Some(before) = from.before {
+                    script_set.before = Some(base_path.join(before));
+                }
+                if let Some(after) = from.after {
+                    script_set.after = Some(base_path.join(after));
+                }
+                changed.push(key.to_string());
+            }
+        }
+
+        for name in overlay.remove_packages {
+            let (removed, kept): (Vec<InnerPackage>, Vec<InnerPackage>) =
+                std::mem::take(&mut self.packages)
+                    .into_iter()
+                    .partition(|inner| inner.name == name);
+            self.packages = kept;
+            for inner in removed {
+                let _ = self.origins.remove(&inner.origin_key());
+            }
+        }
+        for inner in overlay.packages {
+            changed.push(inner.origin_key());
+            match self.packages.iter_mut().find(|existing| {
+                existing.name == inner.name && existing.installer == inner.installer
+            }) {
+                Some(existing) => *existing = inner,
+                None => self.packages.push(inner),
+            }
+        }
+
+        for key in changed {
+            match origin {
+                Some(origin) => {
+                    let _ = self.origins.insert(key, origin.to_path_buf());
+                }
+                None => {
+                    let _ = self.origins.remove(&key);
+                }
+            }
+        }
+    }
+
+    ///
+    /// Make the paths of this package's scripts absolute, so that they still refer to the same
+    /// files when the package is used as the base of one in another directory.
+    ///
+    pub(super) fn resolve_scripts(&mut self) {
+        let base_path = self
+            .path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .to_path_buf();
+        for script_set in vec![
+            &mut self.on_install,
+            &mut self.on_update,
+            &mut self.on_delete,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            for script in vec![&mut script_set.before, &mut script_set.after]
+                .into_iter()
+                .flatten()
+            {
+                *script = base_path.join(&*script);
+            }
+        }
+    }
+
+    fn script_set_mut(&mut self, kind: ScriptSetKind) -> (&'static str, &mut Option<ScriptSet>) {
+        match kind {
+            ScriptSetKind::Install => ("on_install", &mut self.on_install),
+            ScriptSetKind::Update => ("on_update", &mut self.on_update),
+            ScriptSetKind::Delete => ("on_delete", &mut self.on_delete),
+        }
+    }
+}
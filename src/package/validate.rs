@@ -0,0 +1,1106 @@
+use crate::config::Configuration;
+use crate::error::{describe_toml_error, Location, Result};
+use crate::name::Name;
+use crate::package::overlay::PackageOverlay;
+use crate::package::{
+    get_package_dirs, package_files, InnerPackage, ScriptSet, DEFAULT_NAME_KEY, EXTENDS,
+    PACKAGE_FILE_NAME, SYSTEM_INSTALLER,
+};
+use serde::de::{Deserialize, Deserializer, Visitor};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use toml_edit::{ImDocument, Item, Key, Table, TableLike, Value};
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// A single problem found while validating a package file, with the location in the file where
+/// it was detected if known.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    pub path: PathBuf,
+    pub location: Option<Location>,
+    pub message: String,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+struct Validator<'a> {
+    config: &'a Configuration,
+    known: &'a HashSet<String>,
+    path: PathBuf,
+    is_overlay: bool,
+    content: String,
+    problems: Vec<Problem>,
+}
+
+///
+/// A string, either a value or a key, in the package file being validated and the span of its
+/// source.
+///
+struct Located<'a> {
+    value: &'a str,
+    span: Range<usize>,
+}
+
+///
+/// A deserializer that only records the names of the fields of the struct deserialized from it,
+/// so that the keys allowed in a package file are always those of the package types.
+///
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+pub(super) fn validate_packages(config: &Configuration) -> Result<Vec<Problem>> {
+    info!("validate_packages(..)");
+    let mut problems: Vec<Problem> = Default::default();
+    let package_dirs = get_package_dirs(config)?;
+    let known = known_names(&package_dirs)?;
+
+    // Every package seen in any root, and the index of the root it was last seen in. A package of
+    // the same name in a later root is an override, not a duplicate; a second one in the same
+    // root is. Names are compared without case, as `Name` does.
+    let mut seen: HashMap<String, (usize, PathBuf)> = Default::default();
+    for (root, parent_path) in package_dirs.iter().enumerate() {
+        for package_file in package_files(parent_path)? {
+            for overlay_file in overlay_files(&package_file)? {
+                let mut validator = Validator {
+                    config,
+                    known: &known,
+                    path: overlay_file.clone(),
+                    is_overlay: true,
+                    content: std::fs::read_to_string(&overlay_file)?,
+                    problems: Default::default(),
+                };
+                let _ = validator.validate();
+                validator
+                    .problems
+                    .sort_by_key(|p| p.location.map(|l| (l.line, l.column)));
+                problems.extend(validator.problems);
+            }
+            let mut validator = Validator {
+                config,
+                known: &known,
+                path: package_file.clone(),
+                is_overlay: false,
+                content: std::fs::read_to_string(&package_file)?,
+                problems: Default::default(),
+            };
+            if let Some(name) = validator.validate() {
+                match seen.get(&name.to_lowercase()) {
+                    Some((other_root, other)) if *other_root == root => {
+                        problems.push(Problem {
+                            path: package_file.clone(),
+                            location: None,
+                            message: format!(
+                                "package '{}' is also defined in {}",
+                                name,
+                                other.to_string_lossy()
+                            ),
+                        });
+                    }
+                    _ => {
+                        let _ = seen.insert(name.to_lowercase(), (root, package_file));
+                    }
+                }
+            }
+            validator
+                .problems
+                .sort_by_key(|p| p.location.map(|l| (l.line, l.column)));
+            problems.extend(validator.problems);
+        }
+    }
+
+    debug!(".. found {} problems", problems.len());
+    Ok(problems)
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.location {
+            Some(location) => write!(
+                f,
+                "{}:{}: {}",
+                self.path.to_string_lossy(),
+                location,
+                self.message
+            ),
+            None => write!(f, "{}: {}", self.path.to_string_lossy(), self.message),
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl<'a> Validator<'a> {
+    fn validate(&mut self) -> Option<String> {
+        trace!("> Validator::validate({:?})", self.path);
+        let document = match ImDocument::parse(self.content.clone()) {
+            Ok(document) => document,
+            Err(err) => {
+                self.problems.push(Problem {
+                    path: self.path.clone(),
+                    location: err
+                        .span()
+                        .map(|span| Location::from_offset(&self.content, span.start)),
+                    message: format!("could not parse package file: {}", err.message()),
+                });
+                return None;
+            }
+        };
+
+        let checked = self.check_keys(&document);
+        if let Err(err) = toml::from_str::<PackageOverlay>(&checked) {
+            let (location, message) = describe_toml_error(&err);
+            self.problems.push(Problem {
+                path: self.path.clone(),
+                location,
+                message: format!("invalid package file: {}", message),
+            });
+        }
+
+        let package = document.as_table();
+        let name = located(package.get("name"));
+        if let Some(name) = &name {
+            self.check_name(name, "package");
+        } else if !self.is_overlay && !package.contains_key("name") {
+            self.missing("name");
+        }
+        if let Some(extends) = located(package.get(EXTENDS)) {
+            self.check_extends(&extends, name.as_ref());
+        } else if !self.is_overlay && !package.contains_key(EXTENDS) {
+            for field in &["layer", "packages"] {
+                if !package.contains_key(field) {
+                    self.missing(field);
+                }
+            }
+            for value in located_all(package.get("remove_packages"))
+                .iter()
+                .chain(&located_all(package.get("remove_scripts")))
+            {
+                self.report(
+                    &value.span,
+                    "only a package that extends another, or an overlay file, may remove values"
+                        .to_string(),
+                );
+            }
+        }
+        if let Some(layer) = located(package.get("layer")) {
+            self.check_layer(&layer);
+        }
+        for tag in located_all(package.get("tags")) {
+            self.check_name(&tag, "tag");
+        }
+        for name in located_all(package.get("remove_packages")) {
+            self.check_name(&name, "inner package");
+        }
+
+        let mut inner_names: HashSet<(String, String)> = Default::default();
+        for inner in tables(package.get("packages")) {
+            let inner_name = located(inner.get("name"));
+            if let Some(inner_name) = &inner_name {
+                self.check_name(inner_name, "inner package");
+            }
+            let installers = self.check_installer(inner);
+            self.check_names(inner);
+            if let Some(inner_name) = inner_name {
+                let installers = installers.join(", ");
+                let key = (inner_name.value.to_lowercase(), installers.to_lowercase());
+                if !installers.is_empty() && !inner_names.insert(key) {
+                    self.report(
+                        &inner_name.span,
+                        format!(
+                            "inner package '{}' is listed more than once for installer '{}'",
+                            inner_name.value, installers
+                        ),
+                    );
+                }
+            }
+        }
+
+        for script_set in SCRIPT_SETS
+            .iter()
+            .filter_map(|script_set| package.get(script_set).and_then(Item::as_table_like))
+        {
+            for script in ["before", "after"]
+                .iter()
+                .filter_map(|script| located(script_set.get(script)))
+            {
+                self.check_script(&script);
+            }
+        }
+
+        name.map(|name| name.value.to_string())
+    }
+
+    ///
+    /// Report every key that is not a field of the package types, and return the content with
+    /// those keys, and their values, blanked out. The rest of the file can then still be checked
+    /// against the package types, and keeps its locations.
+    ///
+    fn check_keys(&mut self, document: &ImDocument<String>) -> String {
+        let mut checked = self.content.clone().into_bytes();
+        let package = document.as_table();
+        self.check_table_keys(
+            package,
+            field_names::<PackageOverlay>(),
+            "package",
+            &mut checked,
+        );
+        for inner in tables(package.get("packages")) {
+            self.check_table_keys(
+                inner,
+                field_names::<InnerPackage>(),
+                "inner package",
+                &mut checked,
+            );
+        }
+        for script_set in SCRIPT_SETS
+            .iter()
+            .filter_map(|script_set| package.get(script_set).and_then(Item::as_table_like))
+        {
+            self.check_table_keys(
+                script_set,
+                field_names::<ScriptSet>(),
+                "script set",
+                &mut checked,
+            );
+        }
+        // Only whole characters were replaced, with spaces, so this is still valid UTF-8.
+        String::from_utf8(checked).unwrap()
+    }
+
+    fn check_table_keys(
+        &mut self,
+        table: &dyn TableLike,
+        fields: &[&str],
+        kind: &str,
+        checked: &mut [u8],
+    ) {
+        for (key, item) in table.iter() {
+            if fields.contains(&key) {
+                continue;
+            }
+            if let Some((key, _)) = table.get_key_value(key) {
+                if let Some(span) = key.span() {
+                    self.report(
+                        &span,
+                        format!(
+                            "unknown {} field `{}`, expected one of {}",
+                            kind,
+                            key.get(),
+                            fields
+                                .iter()
+                                .map(|field| format!("`{}`", field))
+                                .collect::<Vec<String>>()
+                                .join(", ")
+                        ),
+                    );
+                }
+                blank_entry(checked, key, item);
+            }
+        }
+    }
+
+    fn check_extends(&mut self, extends: &Located<'_>, name: Option<&Located<'_>>) {
+        if self.is_overlay {
+            self.report(
+                &extends.span,
+                "an overlay file may not extend another package".to_string(),
+            );
+            return;
+        }
+        if !self.check_name(extends, "package") {
+            return;
+        }
+        let extends_self = name
+            .map(|name| name.value.eq_ignore_ascii_case(extends.value))
+            .unwrap_or_default();
+        if extends_self {
+            self.report(&extends.span, "a package may not extend itself".to_string());
+        } else if !self.known.contains(&extends.value.to_lowercase()) {
+            self.report(
+                &extends.span,
+                format!("extended package '{}' does not exist", extends.value),
+            );
+        }
+    }
+
+    fn check_name(&mut self, name: &Located<'_>, kind: &str) -> bool {
+        if Name::is_valid(name.value) {
+            true
+        } else {
+            self.report(
+                &name.span,
+                format!("invalid {} name '{}'", kind, name.value),
+            );
+            false
+        }
+    }
+
+    fn check_layer(&mut self, layer: &Located<'_>) {
+        if !self.check_name(layer, "layer") {
+            return;
+        }
+        let name: Name = layer.value.parse().unwrap();
+        match self.config.find_layer(&name) {
+            Some(found) if found.is_inner() => {}
+            Some(_) => self.report(
+                &layer.span,
+                format!(
+                    "layer '{}' is an outer layer, packages must use an inner layer",
+                    name
+                ),
+            ),
+            None => self.report(&layer.span, format!("layer '{}' does not exist", name)),
+        }
+    }
+
+    ///
+    /// Check the installer, or list of fallback installers, of `inner` and return their names.
+    /// With a single installer each platform must be supported by it, with a list each must be
+    /// supported by at least one of them.
+    ///
+    fn check_installer(&mut self, inner: &dyn TableLike) -> Vec<String> {
+        let (installers, span) = match inner.get("installer") {
+            Some(item) => match (located(Some(item)), item.span()) {
+                (Some(installer), _) => (vec![installer], None),
+                (None, Some(span)) if item.is_array() => (located_all(Some(item)), Some(span)),
+                // Any other value is not a valid `InstallerChain`, and is reported as such.
+                _ => return Default::default(),
+            },
+            None => return Default::default(),
+        };
+        let names: Vec<String> = installers
+            .iter()
+            .map(|installer| installer.value.to_string())
+            .collect();
+
+        let mut platforms: Vec<Name> = Default::default();
+        for platform in located_all(inner.get("platforms")) {
+            if self.check_name(&platform, "platform") {
+                platforms.push(platform.value.parse().unwrap());
+            }
+        }
+
+        // The platforms supported by each installer, `None` for those that support any.
+        let mut supported: Vec<(Name, Option<Vec<Name>>)> = Default::default();
+        for installer in &installers {
+            if !self.check_name(installer, "installer") {
+                continue;
+            }
+            let name: Name = installer.value.parse().unwrap();
+            if installer.value == SYSTEM_INSTALLER {
+                supported.push((name, None));
+                continue;
+            }
+            match self.config.installer(&name) {
+                Some(found) if found.platforms.is_empty() => supported.push((name, None)),
+                Some(found) => {
+                    let platforms = found.platforms.clone();
+                    supported.push((name, Some(platforms)))
+                }
+                None => self.report(
+                    &installer.span,
+                    format!("installer '{}' does not exist", name),
+                ),
+            }
+        }
+        if installers.is_empty() || supported.len() != installers.len() {
+            return names;
+        }
+        let supports = |platform: &Name| {
+            supported.iter().any(|(_, platforms)| {
+                platforms
+                    .as_ref()
+                    .map(|platforms| platforms.contains(platform))
+                    .unwrap_or(true)
+            })
+        };
+        let described = if names.len() == 1 {
+            format!("installer '{}' does not support", names[0])
+        } else {
+            format!("none of the installers '{}' support", names.join(", "))
+        };
+        let span = span.unwrap_or_else(|| installers[0].span.clone());
+
+        if platforms.is_empty() {
+            let current = &self.config.platform().target_os;
+            if !supports(current) {
+                self.report(
+                    &span,
+                    format!(
+                        "{} the current platform '{}', add a `platforms` list",
+                        described, current
+                    ),
+                );
+            }
+        } else {
+            for platform in platforms.iter().filter(|p| !supports(p)) {
+                self.report(
+                    &span,
+                    format!("{} the declared platform '{}'", described, platform),
+                );
+            }
+        }
+        names
+    }
+
+    fn check_names(&mut self, inner: &dyn TableLike) {
+        let names = match inner.get("names").and_then(Item::as_table_like) {
+            Some(names) => names,
+            None => return,
+        };
+        for (installer, name) in entries(names) {
+            if let Some(name) = located(Some(name)) {
+                if name.value.trim().is_empty() {
+                    self.report(
+                        &name.span,
+                        "inner package name may not be empty".to_string(),
+                    );
+                }
+            }
+            if !self.check_name(&installer, "installer")
+                || installer.value.eq_ignore_ascii_case(DEFAULT_NAME_KEY)
+            {
+                continue;
+            }
+            if installer.value.eq_ignore_ascii_case(SYSTEM_INSTALLER) {
+                self.report(
+                    &installer.span,
+                    format!(
+                        "names are chosen by the resolved installer, not '{}'",
+                        SYSTEM_INSTALLER
+                    ),
+                );
+            } else if self
+                .config
+                .installer(&installer.value.parse().unwrap())
+                .is_none()
+            {
+                self.report(
+                    &installer.span,
+                    format!("installer '{}' does not exist", installer.value),
+                );
+            }
+        }
+    }
+
+    fn check_script(&mut self, script: &Located<'_>) {
+        let script_path = PathBuf::from(script.value);
+        let script_path = if script_path.is_absolute() {
+            script_path
+        } else {
+            match self.path.parent() {
+                None => script_path,
+                Some(base_path) => base_path.join(script_path),
+            }
+        };
+        if !script_path.is_file() {
+            self.report(
+                &script.span,
+                format!("script file {:?} does not exist", script_path),
+            );
+        } else if !is_executable(&script_path) {
+            self.report(
+                &script.span,
+                format!("script file {:?} is not executable", script_path),
+            );
+        }
+    }
+
+    fn missing(&mut self, field: &str) {
+        self.problems.push(Problem {
+            path: self.path.clone(),
+            location: None,
+            message: format!("missing field `{}`", field),
+        });
+    }
+
+    fn report(&mut self, span: &Range<usize>, message: String) {
+        self.problems.push(Problem {
+            path: self.path.clone(),
+            location: Some(Location::from_offset(&self.content, span.start)),
+            message,
+        });
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl<'de> Deserializer<'de> for FieldNames<'_> {
+    type Error = serde::de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> std::result::Result<V::Value, Self::Error> {
+        Err(serde::de::Error::custom(
+            "only struct field names are recorded",
+        ))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        self.deserialize_any(visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+        ignored_any
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+const SCRIPT_SETS: &[&str] = &["on_install", "on_update", "on_delete"];
+
+///
+/// The names of the fields of `T`, as they are written in a package file.
+///
+fn field_names<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+fn located(item: Option<&Item>) -> Option<Located<'_>> {
+    let item = item?;
+    Some(Located {
+        value: item.as_str()?,
+        span: item.span()?,
+    })
+}
+
+///
+/// The strings in `item`, if it is an array; any other values are left for the check against
+/// the package types to report.
+///
+fn located_all(item: Option<&Item>) -> Vec<Located<'_>> {
+    item.and_then(Item::as_array)
+        .map(|array| {
+            array
+                .iter()
+                .filter_map(|value| {
+                    Some(Located {
+                        value: value.as_str()?,
+                        span: value.span()?,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+///
+/// The tables in `item`, whether written as an array of tables or as an array of inline tables.
+///
+fn tables(item: Option<&Item>) -> Vec<&dyn TableLike> {
+    match item {
+        Some(Item::ArrayOfTables(tables)) => {
+            tables.iter().map(|table| table as &dyn TableLike).collect()
+        }
+        Some(Item::Value(Value::Array(array))) => array
+            .iter()
+            .filter_map(|value| value.as_inline_table())
+            .map(|table| table as &dyn TableLike)
+            .collect(),
+        _ => Default::default(),
+    }
+}
+
+fn entries(table: &dyn TableLike) -> Vec<(Located<'_>, &Item)> {
+    table
+        .iter()
+        .filter_map(|(key, _)| {
+            let (key, item) = table.get_key_value(key)?;
+            Some((
+                Located {
+                    value: key.get(),
+                    span: key.span()?,
+                },
+                item,
+            ))
+        })
+        .collect()
+}
+
+///
+/// Blank out the entry for `key` in `content`, including the separating comma if it is within
+/// an inline table, or the header and every entry if it is a table.
+///
+fn blank_entry(content: &mut [u8], key: &Key, item: &Item) {
+    match item {
+        Item::Table(table) if !table.is_dotted() => blank_table(content, table),
+        Item::ArrayOfTables(tables) => tables.iter().for_each(|table| blank_table(content, table)),
+        _ => {
+            if let (Some(span), Some(end)) = (key.span(), item_end(item)) {
+                let mut start = span.start;
+                let mut end = end;
+                let is_blank = |byte: &u8| *byte == b' ' || *byte == b'\t';
+                let before = content[..start]
+                    .iter()
+                    .rev()
+                    .take_while(|b| is_blank(b))
+                    .count();
+                let after = content[end..].iter().take_while(|b| is_blank(b)).count();
+                if start > before && content[start - before - 1] == b',' {
+                    start -= before + 1;
+                } else if content.get(end + after) == Some(&b',') {
+                    end += after + 1;
+                }
+                blank(content, start..end);
+            }
+        }
+    }
+}
+
+fn blank_table(content: &mut [u8], table: &Table) {
+    if let Some(span) = table.span() {
+        blank(content, span);
+    }
+    for (key, _) in table.iter() {
+        if let Some((key, item)) = table.get_key_value(key) {
+            blank_entry(content, key, item);
+        }
+    }
+}
+
+fn item_end(item: &Item) -> Option<usize> {
+    match item {
+        Item::Value(value) => value.span().map(|span| span.end),
+        Item::Table(table) => table.iter().filter_map(|(_, item)| item_end(item)).max(),
+        _ => None,
+    }
+}
+
+///
+/// Replace every character within `span` of `content` with a space, except line breaks, so that
+/// everything after it keeps its location.
+///
+fn blank(content: &mut [u8], span: Range<usize>) {
+    for byte in &mut content[span] {
+        if *byte != b'\n' && *byte != b'\r' {
+            *byte = b' ';
+        }
+    }
+}
+
+///
+/// The names, in lower case, of all packages in every package root; only the name is read from
+/// each file so that one broken package does not hide the others.
+///
+fn known_names(package_dirs: &[PathBuf]) -> Result<HashSet<String>> {
+    let mut names: HashSet<String> = Default::default();
+    for parent_path in package_dirs {
+        for package_file in package_files(parent_path)? {
+            let content = std::fs::read_to_string(&package_file)?;
+            if let Some(name) = toml::from_str::<toml::Value>(&content)
+                .ok()
+                .and_then(|value| value.get("name").and_then(|v| v.as_str()).map(String::from))
+            {
+                let _ = names.insert(name.to_lowercase());
+            }
+        }
+    }
+    Ok(names)
+}
+
+///
+/// Any overlay files, `package.<suffix>.toml`, alongside `package_file`; for every platform and
+/// host, not only the current ones.
+///
+fn overlay_files(package_file: &Path) -> Result<Vec<PathBuf>> {
+    let stem = PACKAGE_FILE_NAME.trim_end_matches(".toml");
+    let mut overlay_files: Vec<PathBuf> = Default::default();
+    if let Some(parent_path) = package_file.parent() {
+        for entry in std::fs::read_dir(parent_path)? {
+            let path = entry?.path();
+            let file_name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            if path.is_file()
+                && file_name.starts_with(&format!("{}.", stem))
+                && file_name.ends_with(".toml")
+                && file_name != PACKAGE_FILE_NAME
+            {
+                overlay_files.push(path);
+            }
+        }
+    }
+    overlay_files.sort();
+    Ok(overlay_files)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    match std::fs::metadata(path) {
+        Ok(metadata) => metadata.permissions().mode() & 0o111 != 0,
+        Err(_) => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestPackageRoot;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    fn problems(package_root: &TestPackageRoot) -> Vec<(Option<Location>, String)> {
+        let config = package_root
+            .builder()
+            .target_os("linux".parse().unwrap())
+            .build()
+            .unwrap();
+        validate_packages(&config)
+            .unwrap()
+            .into_iter()
+            .map(|problem| (problem.location, problem.message))
+            .collect()
+    }
+
+    fn problems_in(content: &str) -> Vec<(Option<Location>, String)> {
+        let package_root = TestPackageRoot::new();
+        let _ = package_root.write_package("Test", content);
+        problems(&package_root)
+    }
+
+    fn at(line: usize, column: usize) -> Option<Location> {
+        Some(Location { line, column })
+    }
+
+    #[test]
+    fn test_valid_package() {
+        assert_eq!(
+            problems_in(
+                r#"name = "test"
+layer = "shell"
+tags = ["cli"]
+packages = [{ name = "fd", installer = ["apt", "cargo"], names = { apt = "fd-find" } }]
+"#
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        let problems = problems_in(
+            r#"name = "test"
+layer = "shell
+packages = []
+"#,
+        );
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].0, at(2, 15));
+        assert!(problems[0].1.starts_with("could not parse package file: "));
+    }
+
+    #[test]
+    fn test_missing_fields() {
+        assert_eq!(
+            problems_in("description = \"nothing else\"\n"),
+            vec![
+                (None, "missing field `name`".to_string()),
+                (None, "missing field `layer`".to_string()),
+                (None, "missing field `packages`".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_fields_keep_checking() {
+        let problems = problems_in(
+            r#"name = "test"
+layer = "no-such-layer"
+summary = "unknown"
+packages = [
+    { name = "fd", installer = "cargo", flavour = "unknown" },
+    { versoin = "1.0", name = "rg", installer = "cargo" },
+]
+
+[on_install]
+befor = "missing.sh"
+"#,
+        );
+        let locations: Vec<Option<Location>> =
+            problems.iter().map(|(location, _)| *location).collect();
+        assert_eq!(
+            locations,
+            vec![at(2, 9), at(3, 1), at(5, 41), at(6, 7), at(10, 1)]
+        );
+        assert_eq!(problems[0].1, "layer 'no-such-layer' does not exist");
+        assert!(problems[1]
+            .1
+            .starts_with("unknown package field `summary`, expected one of `name`, "));
+        assert!(problems[2]
+            .1
+            .starts_with("unknown inner package field `flavour`, expected one of `name`, "));
+        assert!(problems[3]
+            .1
+            .starts_with("unknown inner package field `versoin`, expected one of `name`, "));
+        assert_eq!(
+            problems[4].1,
+            "unknown script set field `befor`, expected one of `before`, `after`"
+        );
+    }
+
+    #[test]
+    fn test_unknown_tables() {
+        let problems = problems_in(
+            r#"name = "test"
+layer = "shell"
+packages = []
+meta.owner = "me"
+
+[extra]
+key = 1
+
+[[more]]
+key = 2
+"#,
+        );
+        let unknown: Vec<(Option<Location>, String)> = problems
+            .into_iter()
+            .map(|(location, message)| (location, message.split(',').next().unwrap().into()))
+            .collect();
+        assert_eq!(
+            unknown,
+            vec![
+                (at(4, 1), "unknown package field `meta`".to_string()),
+                (at(6, 2), "unknown package field `extra`".to_string()),
+                (at(9, 3), "unknown package field `more`".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_value_keeps_checking() {
+        let problems = problems_in(
+            r#"name = "test"
+layer = "system"
+packages = [{ name = "fd", installer = "cargo", is_app = "yes" }]
+"#,
+        );
+        assert_eq!(problems.len(), 2);
+        assert_eq!(
+            problems[0],
+            (
+                at(2, 9),
+                "layer 'system' is an outer layer, packages must use an inner layer".to_string()
+            )
+        );
+        assert_eq!(problems[1].0, at(3, 58));
+        assert!(problems[1].1.starts_with("invalid package file: "));
+    }
+
+    #[test]
+    fn test_invalid_names() {
+        assert_eq!(
+            problems_in(
+                r#"name = "not a name"
+layer = "shell"
+tags = ["cli", "not a tag"]
+packages = [{ name = "not-inner!", installer = "cargo" }]
+"#
+            ),
+            vec![
+                (at(1, 8), "invalid package name 'not a name'".to_string()),
+                (at(3, 16), "invalid tag name 'not a tag'".to_string()),
+                (
+                    at(4, 22),
+                    "invalid inner package name 'not-inner!'".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_installers() {
+        assert_eq!(
+            problems_in(
+                r#"name = "test"
+layer = "shell"
+
+[[packages]]
+name = "fd"
+installer = ["no-such-installer", "cargo"]
+
+[[packages]]
+name = "bat"
+installer = "homebrew"
+
+[[packages]]
+name = "rg"
+installer = ["homebrew", "snap"]
+platforms = ["linux", "macos", "windows"]
+names = { system = "ripgrep", nope = "rg", apt = " " }
+
+[[packages]]
+name = "RG"
+installer = ["Homebrew", "Snap"]
+platforms = ["linux", "macos"]
+"#
+            ),
+            vec![
+                (
+                    at(6, 14),
+                    "installer 'no-such-installer' does not exist".to_string()
+                ),
+                (
+                    at(10, 13),
+                    "installer 'homebrew' does not support the current platform 'linux', \
+                     add a `platforms` list"
+                        .to_string()
+                ),
+                (
+                    at(14, 13),
+                    "none of the installers 'homebrew, snap' support the declared platform \
+                     'windows'"
+                        .to_string()
+                ),
+                (
+                    at(16, 11),
+                    "names are chosen by the resolved installer, not 'system'".to_string()
+                ),
+                (at(16, 31), "installer 'nope' does not exist".to_string()),
+                (
+                    at(16, 50),
+                    "inner package name may not be empty".to_string()
+                ),
+                (
+                    at(19, 8),
+                    "inner package 'RG' is listed more than once for installer 'Homebrew, Snap'"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extends_and_overlays() {
+        let package_root = TestPackageRoot::new();
+        let _ = package_root.write_package(
+            "Base",
+            r#"name = "base"
+layer = "shell"
+packages = []
+remove_packages = ["fd"]
+"#,
+        );
+        let _ = package_root.write_package(
+            "Itself",
+            r#"name = "itself"
+extends = "Itself"
+"#,
+        );
+        let _ = package_root.write_package(
+            "Other",
+            r#"name = "other"
+extends = "no-such-package"
+"#,
+        );
+        std::fs::write(
+            package_root.path().join("Base/package.linux.toml"),
+            "extends = \"other\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            problems(&package_root),
+            vec![
+                (
+                    at(1, 11),
+                    "an overlay file may not extend another package".to_string()
+                ),
+                (
+                    at(4, 20),
+                    "only a package that extends another, or an overlay file, may remove values"
+                        .to_string()
+                ),
+                (at(2, 11), "a package may not extend itself".to_string()),
+                (
+                    at(2, 11),
+                    "extended package 'no-such-package' does not exist".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scripts() {
+        let package_root = TestPackageRoot::new();
+        let package_file = package_root.write_package(
+            "Test",
+            r#"name = "test"
+layer = "shell"
+packages = []
+on_install = { before = "missing.sh", after = "not-executable.sh" }
+"#,
+        );
+        let script = package_file.parent().unwrap().join("not-executable.sh");
+        std::fs::write(&script, "#!/bin/sh\n").unwrap();
+        let problems = problems(&package_root);
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].0, at(4, 25));
+        assert!(problems[0].1.ends_with("missing.sh\" does not exist"));
+        assert_eq!(problems[1].0, at(4, 47));
+        assert!(problems[1]
+            .1
+            .ends_with("not-executable.sh\" is not executable"));
+    }
+
+    #[test]
+    fn test_duplicates_across_roots() {
+        let earlier = TestPackageRoot::new();
+        let later = TestPackageRoot::new();
+        let package = "name = \"fish\"\nlayer = \"shell\"\npackages = []\n";
+        let _ = earlier.write_package("Fish", package);
+        let _ = later.write_package("Fish", package);
+        let config = earlier.builder().package_dir(later.path()).build().unwrap();
+        assert!(validate_packages(&config).unwrap().is_empty());
+
+        let duplicate = later.write_package("Other-Fish", &package.replace("fish", "FISH"));
+        let problems = validate_packages(&config).unwrap();
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].path, duplicate);
+        assert_eq!(problems[0].location, None);
+        assert_eq!(
+            problems[0].message,
+            format!(
+                "package 'FISH' is also defined in {}",
+                later.path().join("Fish/package.toml").to_string_lossy()
+            )
+        );
+    }
+}
@@ -0,0 +1,273 @@
+/*!
+Serializable reports produced by the read-only commands, so that their results may be consumed by
+scripts and other tools rather than read from the screen.
+
+Each report may be rendered as JSON, TOML, or YAML with `render`; the `Text` format is left to
+the caller, as the command-line tool has its own human-readable presentation.
+
+# Example
+
+```rust,no_run
+use packpack::config::current_configuration;
+use packpack::report::{render, ConfigReport};
+use packpack::OutputFormat;
+
+let config = current_configuration().as_ref().unwrap();
+println!("{}", render(&ConfigReport::new(config), OutputFormat::Json).unwrap());
+```
+
+*/
+
+use crate::config::{Configuration, LayerKind};
+use crate::error::{Location, Result};
+use crate::name::Name;
+use crate::package::{InnerPackage, Package, Problem, ScriptSet};
+use crate::OutputFormat;
+use serde::Serialize;
+use std::iter::FromIterator;
+use std::path::PathBuf;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The current platform, layer tree, and installers available on the current platform.
+///
+#[derive(Clone, Debug, Serialize)]
+pub struct ConfigReport {
+    pub platform: PlatformReport,
+    pub layers: Vec<LayerReport>,
+    pub installers: Vec<InstallerReport>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PlatformReport {
+    pub name: Name,
+    pub target_os: Name,
+    pub system_installer: Option<Name>,
+    pub app_installer: Option<Name>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LayerReport {
+    pub name: Name,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sub_layers: Vec<LayerReport>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct InstallerReport {
+    pub id: Name,
+    pub name: Name,
+    pub is_system: bool,
+}
+
+///
+/// A set of packages, as returned by inspect or search.
+///
+#[derive(Clone, Debug, Serialize)]
+pub struct PackagesReport {
+    pub packages: Vec<PackageReport>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PackageReport {
+    pub name: Name,
+    pub layer: Name,
+    pub path: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extends: Option<Name>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub tags: Vec<Name>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maintainer: Option<String>,
+    pub requires: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_install: Option<ScriptSet>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_update: Option<ScriptSet>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_delete: Option<ScriptSet>,
+    pub packages: Vec<InnerPackage>,
+}
+
+///
+/// The problems found by validating all packages.
+///
+#[derive(Clone, Debug, Serialize)]
+pub struct ProblemsReport {
+    pub problems: Vec<ProblemReport>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ProblemReport {
+    pub path: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+    pub message: String,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Serialize `report` in the structured `format`; `Text` is rendered as TOML.
+///
+pub fn render<T: Serialize>(report: &T, format: OutputFormat) -> Result<String> {
+    match format {
+        OutputFormat::Text | OutputFormat::Toml => {
+            // Converting to a value first ensures simple values are written before tables.
+            let value = toml::Value::try_from(report)?;
+            Ok(toml::to_string(&value)?)
+        }
+        OutputFormat::Json => Ok(serde_json::to_string_pretty(report)?),
+        OutputFormat::Yaml => Ok(serde_yaml::to_string(report)?),
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl ConfigReport {
+    pub fn new(config: &Configuration) -> Self {
+        let platform = config.platform();
+        let mut installers: Vec<InstallerReport> = config
+            .installers()
+            .map(|(id, installer)| InstallerReport {
+                id: id.clone(),
+                name: installer.name.clone(),
+                is_system: Some(id) == platform.system_installer.as_ref(),
+            })
+            .collect();
+        installers.sort_by_key(|installer| installer.id.to_string());
+        Self {
+            platform: PlatformReport {
+                name: platform.name.clone(),
+                target_os: platform.target_os.clone(),
+                system_installer: platform.system_installer.clone(),
+                app_installer: platform.app_installer.clone(),
+            },
+            layers: config
+                .layers()
+                .map(|layer| LayerReport::new(config, layer))
+                .collect(),
+            installers,
+        }
+    }
+}
+
+impl LayerReport {
+    fn new(config: &Configuration, layer: &LayerKind) -> Self {
+        Self {
+            name: layer.name().clone(),
+            description: config
+                .layer_definition(layer.name())
+                .and_then(|definition| definition.description.clone()),
+            sub_layers: layer
+                .sub_layers()
+                .iter()
+                .map(|sub_layer| LayerReport::new(config, sub_layer))
+                .collect(),
+        }
+    }
+}
+
+impl From<&Package> for PackageReport {
+    fn from(package: &Package) -> Self {
+        Self {
+            name: package.name.clone(),
+            layer: package.layer.clone(),
+            path: package.path.clone(),
+            extends: package.extends.clone(),
+            overrides: package
+                .overrides()
+                .into_iter()
+                .map(|path| path.to_path_buf())
+                .collect(),
+            description: package.description.clone(),
+            tags: package.tags.clone(),
+            homepage: package.homepage.clone(),
+            maintainer: package.maintainer.clone(),
+            requires: package.requires.clone(),
+            on_install: package.on_install.clone(),
+            on_update: package.on_update.clone(),
+            on_delete: package.on_delete.clone(),
+            packages: package.packages.clone(),
+        }
+    }
+}
+
+impl<'a> FromIterator<&'a Package> for PackagesReport {
+    fn from_iter<I: IntoIterator<Item = &'a Package>>(iter: I) -> Self {
+        Self {
+            packages: iter.into_iter().map(PackageReport::from).collect(),
+        }
+    }
+}
+
+impl From<&Problem> for ProblemReport {
+    fn from(problem: &Problem) -> Self {
+        Self {
+            path: problem.path.clone(),
+            location: problem.location,
+            message: problem.message.clone(),
+        }
+    }
+}
+
+impl<'a> FromIterator<&'a Problem> for ProblemsReport {
+    fn from_iter<I: IntoIterator<Item = &'a Problem>>(iter: I) -> Self {
+        Self {
+            problems: iter.into_iter().map(ProblemReport::from).collect(),
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::Selection;
+    use crate::testing::test_configuration;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[test]
+    fn test_render_config_report() {
+        let report = ConfigReport::new(&test_configuration());
+        for format in &[OutputFormat::Toml, OutputFormat::Json, OutputFormat::Yaml] {
+            assert!(render(&report, *format).is_ok());
+        }
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&report, OutputFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["platform"]["target_os"], "linux");
+        assert_eq!(json["layers"][0]["name"], "bootstrap");
+    }
+
+    #[test]
+    fn test_render_packages_report() {
+        let config = test_configuration();
+        let packages = Selection::all().resolve(&config).unwrap();
+        let report: PackagesReport = packages.iter().collect();
+        let toml = render(&report, OutputFormat::Toml).unwrap();
+        let value: toml::Value = toml::from_str(&toml).unwrap();
+        assert_eq!(
+            value["packages"][0]["name"],
+            toml::Value::String("Rust".to_string())
+        );
+        assert!(render(&report, OutputFormat::Yaml).is_ok());
+    }
+}
@@ -0,0 +1,614 @@
+/*!
+Resolves the names given to the install, update, delete, and inspect commands into the set of
+packages to act upon.
+
+A selection may name layers, packages, or a mix of both; a layer selects every package in any
+inner layer beneath it. A package may be qualified as `layer/package` to ensure it is within that
+layer, or as `package:inner` to act on only one of its inner packages, and any part of a name may
+be a glob pattern such as `dev-*` or `system/font?`. The selected packages may then be filtered to
+those with any of a set of tags, and their inner packages to only those matching a set of
+patterns.
+
+Packages are always returned in layer order, and then by name, so that install and update see
+the same packages in the same order; delete acts in the reverse order so that nothing is removed
+before the packages that depend upon it. Each layer containing a selected package runs its own
+hooks once, around all of its selected packages.
+
+# Example
+
+```rust,no_run
+use packpack::config::current_configuration;
+use packpack::selection::{Scope, Selection};
+
+let config = current_configuration().as_ref().unwrap();
+let selection = Selection::new(Scope::Names(vec!["dev*".parse().unwrap()]))
+    .only(vec!["nu".parse().unwrap()]);
+for package in selection.resolve(config).unwrap() {
+    println!("{}", package.name);
+}
+```
+
+*/
+
+use crate::config::{Configuration, LayerKind};
+use crate::error::{ErrorKind, Result};
+use crate::name::{Name, NamePattern, QualifiedPattern};
+use crate::package::{get_packages, install_action, layer_action, Action, Package, Session};
+use crate::{Installable, Options};
+use std::collections::{HashMap, HashSet};
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The names, or patterns, that select packages.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum Scope {
+    /// Every package, in every layer.
+    All,
+    /// Each name may be either a layer or a package; a qualified name is always a package.
+    Names(Vec<QualifiedPattern>),
+    /// Each name must be a package.
+    Packages(Vec<QualifiedPattern>),
+    /// Each name must be a layer.
+    Layers(Vec<NamePattern>),
+}
+
+///
+/// The packages an action applies to; a scope, optionally filtered by tag and by inner package.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Selection {
+    scope: Scope,
+    tags: Vec<Name>,
+    only: Vec<NamePattern>,
+    installer: Option<Name>,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Types
+// ------------------------------------------------------------------------------------------------
+
+struct Resolver<'a> {
+    config: &'a Configuration,
+    packages: &'a HashMap<Name, Package>,
+    layer_names: Vec<&'a Name>,
+    selected: Vec<Name>,
+    whole: HashSet<Name>,
+    inner_only: HashMap<Name, Vec<NamePattern>>,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Installable for Selection {
+    fn install(&self, config: &Configuration, options: &Options) -> Result<()> {
+        info!("Selection::install({:?})", options);
+        self.act(Action::Install, config, options)
+    }
+
+    fn update(&self, config: &Configuration, options: &Options) -> Result<()> {
+        info!("Selection::update({:?})", options);
+        self.act(Action::Update, config, options)
+    }
+
+    fn delete(&self, config: &Configuration, options: &Options) -> Result<()> {
+        info!("Selection::delete({:?})", options);
+        self.act(Action::Delete, config, options)
+    }
+}
+
+impl From<Scope> for Selection {
+    fn from(scope: Scope) -> Self {
+        Self::new(scope)
+    }
+}
+
+impl Selection {
+    pub fn new(scope: Scope) -> Self {
+        Self {
+            scope,
+            tags: Default::default(),
+            only: Default::default(),
+            installer: None,
+        }
+    }
+
+    pub fn all() -> Self {
+        Self::new(Scope::All)
+    }
+
+    /// Keep only packages with at least one of `tags`.
+    pub fn with_tags(mut self, tags: Vec<Name>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Keep only inner packages matching at least one of `inner`.
+    pub fn only(mut self, inner: Vec<NamePattern>) -> Self {
+        self.only = inner;
+        self
+    }
+
+    /// Keep only inner packages with `installer` in their list of installers.
+    pub fn with_installer(mut self, installer: Name) -> Self {
+        self.installer = Some(installer);
+        self
+    }
+
+    pub fn scope(&self) -> &Scope {
+        &self.scope
+    }
+
+    ///
+    /// Resolve this selection into the packages it contains, each package appears only once.
+    /// Any name that does not match a layer or package, as appropriate, or pattern that matches
+    /// nothing, is an error.
+    ///
+    pub fn resolve(&self, config: &Configuration) -> Result<Vec<Package>> {
+        info!("Selection::resolve({:?})", self);
+        let mut packages = get_packages(config)?;
+        let (mut selected, whole, inner_only) = {
+            let mut resolver = Resolver {
+                config,
+                packages: &packages,
+                layer_names: config
+                    .layers()
+                    .flat_map(|layer| layer.all_names())
+                    .collect(),
+                selected: Default::default(),
+                whole: Default::default(),
+                inner_only: Default::default(),
+            };
+            match &self.scope {
+                Scope::All => {
+                    for name in packages.keys() {
+                        resolver.select_whole(name);
+                    }
+                }
+                Scope::Names(patterns) => {
+                    for pattern in patterns {
+                        resolver.select(pattern, &self.scope)?;
+                    }
+                }
+                Scope::Packages(patterns) => {
+                    for pattern in patterns {
+                        resolver.select(pattern, &self.scope)?;
+                    }
+                }
+                Scope::Layers(patterns) => {
+                    for pattern in patterns {
+                        resolver.select(&pattern.clone().into(), &self.scope)?;
+                    }
+                }
+            }
+            (resolver.selected, resolver.whole, resolver.inner_only)
+        };
+
+        for (name, patterns) in inner_only {
+            if !whole.contains(&name) {
+                if let Some(package) = packages.get_mut(&name) {
+                    package
+                        .packages
+                        .retain(|inner| patterns.iter().any(|p| p.matches(&inner.name)));
+                }
+            }
+        }
+
+        if !self.tags.is_empty() {
+            selected.retain(|name| {
+                packages[name]
+                    .tags
+                    .iter()
+                    .any(|tag| self.tags.contains(tag))
+            });
+        }
+
+        if !self.only.is_empty() {
+            for pattern in &self.only {
+                if !selected.iter().any(|name| {
+                    packages[name]
+                        .packages
+                        .iter()
+                        .any(|inner| pattern.matches(&inner.name))
+                }) {
+                    error!(
+                        "no selected package has an inner package matching {}",
+                        pattern
+                    );
+                    return Err(ErrorKind::NoMatchingNames(pattern.to_string()).into());
+                }
+            }
+            for package in packages.values_mut() {
+                package
+                    .packages
+                    .retain(|inner| self.only.iter().any(|p| p.matches(&inner.name)));
+            }
+            selected.retain(|name| !packages[name].packages.is_empty());
+        }
+
+        if let Some(installer) = &self.installer {
+            for package in packages.values_mut() {
+                package
+                    .packages
+                    .retain(|inner| inner.installer.contains(installer));
+            }
+            selected.retain(|name| !packages[name].packages.is_empty());
+        }
+
+        let layer_order: HashMap<&Name, usize> = config
+            .layers()
+            .flat_map(|layer| layer.leaves())
+            .enumerate()
+            .map(|(index, name)| (name, index))
+            .collect();
+        selected.sort_by_key(|name| {
+            let package = &packages[name];
+            (
+                layer_order.get(&package.layer).copied(),
+                package.name.to_string(),
+            )
+        });
+        selected.dedup();
+        debug!(".. selected packages {:?}", selected);
+
+        Ok(selected
+            .iter()
+            .filter_map(|name| packages.remove(name))
+            .collect())
+    }
+
+    fn act(&self, action: Action, config: &Configuration, options: &Options) -> Result<()> {
+        let packages = self.resolve(config)?;
+        let mut session = Session::for_config(config)?;
+        let mut layers: Vec<&LayerKind> = config.layers().collect();
+        if action == Action::Delete {
+            layers.reverse();
+        }
+        for layer in layers {
+            act_on_layer(action, layer, &packages, config, &mut session, options)?;
+        }
+        Ok(())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+
+impl<'a> Resolver<'a> {
+    fn select(&mut self, pattern: &QualifiedPattern, scope: &Scope) -> Result<()> {
+        trace!("> Resolver::select({})", pattern);
+        let allow_layers = !matches!(scope, Scope::Packages(_));
+        let allow_packages = !matches!(scope, Scope::Layers(_));
+        let mut found = false;
+        let mut partial = false;
+
+        if let (true, Some(name)) = (allow_layers, pattern.as_simple()) {
+            for layer_name in self.layer_names.clone() {
+                if name.matches(layer_name) {
+                    if let Some(layer) = self.config.find_layer(layer_name) {
+                        let leaves = layer.leaves();
+                        for package in self.packages.values() {
+                            if leaves.contains(&&package.layer) {
+                                self.select_whole(&package.name);
+                            }
+                        }
+                        found = true;
+                    }
+                }
+            }
+        }
+
+        if allow_packages {
+            let within = match &pattern.layer {
+                None => None,
+                Some(layer_pattern) => Some(self.leaves_matching(layer_pattern)?),
+            };
+            let matching: Vec<&Package> = self
+                .packages
+                .values()
+                .filter(|package| pattern.name.matches(&package.name))
+                .collect();
+            for package in &matching {
+                if let Some(leaves) = &within {
+                    if !leaves.contains(&&package.layer) {
+                        continue;
+                    }
+                }
+                partial = true;
+                match &pattern.inner {
+                    None => self.select_whole(&package.name),
+                    Some(inner) => {
+                        if package.packages.iter().any(|i| inner.matches(&i.name)) {
+                            self.inner_only
+                                .entry(package.name.clone())
+                                .or_default()
+                                .push(inner.clone());
+                            self.selected.push(package.name.clone());
+                        } else if let Some(inner_name) = inner.as_name() {
+                            if pattern.name.is_literal() {
+                                return Err(ErrorKind::NoSuchInnerPackage(
+                                    package.name.clone(),
+                                    inner_name.clone(),
+                                    inner_name
+                                        .suggestions(package.packages.iter().map(|i| &i.name)),
+                                )
+                                .into());
+                            }
+                            continue;
+                        } else {
+                            continue;
+                        }
+                    }
+                }
+                found = true;
+            }
+            if let (false, Some(package), Some(layer)) = (
+                found,
+                matching.first(),
+                pattern.layer.as_ref().and_then(|l| l.as_name()),
+            ) {
+                if pattern.name.is_literal() {
+                    return Err(
+                        ErrorKind::PackageNotInLayer(package.name.clone(), layer.clone()).into(),
+                    );
+                }
+            }
+        }
+
+        if found {
+            Ok(())
+        } else {
+            error!("nothing selected by the name {}", pattern);
+            Err(match pattern.name.as_name() {
+                Some(_) if partial => ErrorKind::NoMatchingNames(pattern.to_string()),
+                Some(name) => {
+                    let package_names = self.packages.keys();
+                    let layer_names = self.layer_names.clone().into_iter();
+                    match scope {
+                        Scope::Packages(_) => {
+                            ErrorKind::NoSuchPackage(name.clone(), name.suggestions(package_names))
+                        }
+                        Scope::Layers(_) => {
+                            ErrorKind::NoSuchLayer(name.clone(), name.suggestions(layer_names))
+                        }
+                        _ => ErrorKind::NoSuchSelection(
+                            name.clone(),
+                            name.suggestions(layer_names.chain(package_names)),
+                        ),
+                    }
+                }
+                None => ErrorKind::NoMatchingNames(pattern.to_string()),
+            }
+            .into())
+        }
+    }
+
+    fn select_whole(&mut self, name: &Name) {
+        self.selected.push(name.clone());
+        let _ = self.whole.insert(name.clone());
+    }
+
+    fn leaves_matching(&self, pattern: &NamePattern) -> Result<Vec<&'a Name>> {
+        let mut leaves: Vec<&'a Name> = Default::default();
+        for layer_name in self.layer_names.iter().filter(|name| pattern.matches(name)) {
+            if let Some(layer) = self.config.find_layer(layer_name) {
+                leaves.extend(layer.leaves());
+            }
+        }
+        if leaves.is_empty() {
+            Err(match pattern.as_name() {
+                Some(name) => ErrorKind::NoSuchLayer(
+                    name.clone(),
+                    name.suggestions(self.layer_names.clone().into_iter()),
+                ),
+                None => ErrorKind::NoMatchingNames(pattern.to_string()),
+            }
+            .into())
+        } else {
+            Ok(leaves)
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Act on those `packages` within `layer`, running the hooks of each layer, at every depth, that
+/// contains at least one of them. Delete works from the last layer and package to the first.
+///
+fn act_on_layer(
+    action: Action,
+    layer: &LayerKind,
+    packages: &[Package],
+    config: &Configuration,
+    session: &mut Session,
+    options: &Options,
+) -> Result<()> {
+    let leaves = layer.leaves();
+    let mut selected: Vec<&Package> = packages
+        .iter()
+        .filter(|package| leaves.contains(&&package.layer))
+        .collect();
+    if selected.is_empty() {
+        return Ok(());
+    }
+    trace!("> act_on_layer({:?}, {}, ..)", action, layer.name());
+
+    let inner = || -> Result<()> {
+        match layer {
+            LayerKind::Outer {
+                name: _,
+                sub_layers,
+            } => {
+                let mut sub_layers: Vec<&LayerKind> = sub_layers.iter().collect();
+                if action == Action::Delete {
+                    sub_layers.reverse();
+                }
+                for sub_layer in sub_layers {
+                    act_on_layer(action, sub_layer, packages, config, session, options)?;
+                }
+            }
+            LayerKind::Inner { name: _ } => {
+                if action == Action::Delete {
+                    selected.reverse();
+                }
+                for package in selected {
+                    install_action(action, package, config, session, options)?;
+                }
+            }
+        }
+        Ok(())
+    };
+
+    match config.layer_definition(layer.name()) {
+        Some(definition) if !definition.supports(&config.platform().target_os) => {
+            info!(
+                "skipping layer {}, not supported on platform {}",
+                layer.name(),
+                config.platform().target_os
+            );
+            Ok(())
+        }
+        Some(definition) => layer_action(action, definition, config, options, inner),
+        None => inner(),
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_configuration;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    fn names(selection: impl Into<Selection>) -> Result<Vec<String>> {
+        Ok(selection
+            .into()
+            .resolve(&test_configuration())?
+            .iter()
+            .map(|package| package.name.to_string())
+            .collect())
+    }
+
+    fn inner_names(selection: impl Into<Selection>) -> Vec<String> {
+        selection
+            .into()
+            .resolve(&test_configuration())
+            .unwrap()
+            .iter()
+            .flat_map(|package| package.packages.iter().map(|i| i.name.to_string()))
+            .collect()
+    }
+
+    fn parse<T: std::str::FromStr>(names: &[&str]) -> Vec<T>
+    where
+        T::Err: std::fmt::Debug,
+    {
+        names.iter().map(|name| name.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_resolve_all() {
+        assert_eq!(names(Scope::All).unwrap(), vec!["Rust"]);
+    }
+
+    #[test]
+    fn test_resolve_mixed_names() {
+        assert_eq!(
+            names(Scope::Names(parse(&["development"]))).unwrap(),
+            vec!["Rust"]
+        );
+        assert_eq!(
+            names(Scope::Names(parse(&["Rust", "programming-languages"]))).unwrap(),
+            vec!["Rust"]
+        );
+        assert!(names(Scope::Names(parse(&["games"]))).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_unknown_names() {
+        assert!(names(Scope::Names(parse(&["no-such-thing"]))).is_err());
+        assert!(names(Scope::Layers(parse(&["Rust"]))).is_err());
+        assert!(names(Scope::Packages(parse(&["development"]))).is_err());
+    }
+
+    #[test]
+    fn test_resolve_qualified_names() {
+        assert_eq!(
+            names(Scope::Names(parse(&["development/rust"]))).unwrap(),
+            vec!["Rust"]
+        );
+        assert!(names(Scope::Names(parse(&["system/rust"]))).is_err());
+        assert_eq!(
+            inner_names(Scope::Packages(parse(&["rust:clippy-preview", "RUST:rls"]))),
+            vec!["clippy-preview", "rls"]
+        );
+        assert!(inner_names(Scope::Packages(parse(&["rust:rls", "rust"]))).len() > 2);
+    }
+
+    #[test]
+    fn test_resolve_patterns() {
+        assert_eq!(names(Scope::Names(parse(&["dev*"]))).unwrap(), vec!["Rust"]);
+        assert_eq!(
+            names(Scope::Names(parse(&["*/r?st"]))).unwrap(),
+            vec!["Rust"]
+        );
+        assert_eq!(
+            inner_names(Scope::Packages(parse(&["rust:r*"]))),
+            vec!["rustfmt", "rust-src", "rls", "release"]
+        );
+        assert_eq!(
+            inner_names(
+                Selection::new(Scope::Packages(parse(&["rust:r*"])))
+                    .with_installer("rustup".parse().unwrap())
+            ),
+            vec!["rustfmt", "rust-src", "rls"]
+        );
+        assert_eq!(
+            names(Scope::Packages(parse(&["rust:cargo-*"])))
+                .unwrap_err()
+                .to_string(),
+            "Nothing matches the pattern 'rust:cargo-*'"
+        );
+        assert!(names(Scope::Names(parse(&["java*"]))).is_err());
+        assert!(names(Scope::Names(parse(&["sys*/rust"]))).is_err());
+    }
+
+    #[test]
+    fn test_resolve_filters() {
+        assert_eq!(
+            names(Selection::all().with_tags(parse(&["CLI"]))).unwrap(),
+            vec!["Rust"]
+        );
+        assert!(names(Selection::all().with_tags(parse(&["gui"])))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            inner_names(Selection::all().only(parse(&["nu", "sk*"]))),
+            vec!["nu", "skim"]
+        );
+        assert!(names(Selection::all().only(parse(&["no-such-inner"]))).is_err());
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let error = names(Scope::Names(parse(&["rsut"]))).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "No layer or package found with the provided name 'rsut', did you mean 'Rust'?"
+        );
+        let error = names(Scope::Packages(parse(&["rust:clipy-preview"]))).unwrap_err();
+        assert!(error
+            .to_string()
+            .ends_with("did you mean 'clippy-preview'?"));
+    }
+}
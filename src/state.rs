@@ -0,0 +1,207 @@
+/*!
+A ledger of the inner packages installed on this machine, recording which installer in each
+inner package's fallback chain actually installed it and under which name.
+
+The ledger is kept in a TOML file, by default `~/.packpack/state.toml`, and is only written
+when an installer succeeds; a dry run never changes it. It is consulted when updating or
+deleting an inner package so that the same installer is used as installed it, even if one
+earlier in the chain has become available since.
+
+# Example
+
+```rust,no_run
+use packpack::config::current_configuration;
+use packpack::state::Ledger;
+
+let config = current_configuration().as_ref().unwrap();
+let ledger = Ledger::for_config(config).unwrap();
+for entry in ledger.entries() {
+    println!("{}/{} installed by {}", entry.package, entry.inner, entry.installer);
+}
+```
+
+*/
+
+use crate::config::Configuration;
+use crate::error::Result;
+use crate::name::Name;
+use crate::package::{InnerPackage, InstallerChain};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The record of installed inner packages, loaded from, and saved to, a single file.
+///
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Ledger {
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    #[serde(default, rename = "installed", skip_serializing_if = "Vec::is_empty")]
+    entries: Vec<LedgerEntry>,
+}
+
+///
+/// A single installed inner package; `installers` is the chain it was declared with, and
+/// `installer` the one that satisfied it. Entries are identified by `package` and `inner` alone,
+/// so that changing the chain does not lose the record of what is installed.
+///
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LedgerEntry {
+    pub package: Name,
+    pub inner: Name,
+    pub installers: InstallerChain,
+    pub installer: Name,
+    pub name: String,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+pub const STATE_FILE_NAME: &str = "state.toml";
+
+///
+/// The ledger file to use when none is configured explicitly; `~/.packpack/state.toml`,
+/// alongside the default package root.
+///
+pub fn default_state_file() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".packpack").join(STATE_FILE_NAME))
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Ledger {
+    ///
+    /// Load the ledger from the configured state file; if there is none the ledger is empty,
+    /// and if none is configured it is also never saved.
+    ///
+    pub fn for_config(config: &Configuration) -> Result<Self> {
+        match config.state_file() {
+            Some(path) => Self::load(path),
+            None => {
+                warn!("no state file, installed packages will not be recorded");
+                Ok(Default::default())
+            }
+        }
+    }
+
+    /// Load the ledger from `path`, which need not exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        info!("Ledger::load({:?})", path);
+        let mut ledger: Ledger = if path.is_file() {
+            toml::from_str(&std::fs::read_to_string(path)?)?
+        } else {
+            Default::default()
+        };
+        ledger.path = Some(path.to_path_buf());
+        Ok(ledger)
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &LedgerEntry> {
+        self.entries.iter()
+    }
+
+    /// The entry for `inner`, within the named package, if it has been installed.
+    pub fn find(&self, package: &Name, inner: &InnerPackage) -> Option<&LedgerEntry> {
+        self.entries.iter().find(|entry| entry.is(package, inner))
+    }
+
+    ///
+    /// Record that `inner`, within the named package, was installed by `installer` as `name`,
+    /// replacing any earlier record of it.
+    ///
+    pub fn record(&mut self, package: &Name, inner: &InnerPackage, installer: &Name, name: &str) {
+        debug!(
+            "Ledger::record({}, {}, {}, {})",
+            package, inner.name, installer, name
+        );
+        let _ = self.forget(package, inner);
+        self.entries.push(LedgerEntry {
+            package: package.clone(),
+            inner: inner.name.clone(),
+            installers: inner.installer.clone(),
+            installer: installer.clone(),
+            name: name.to_string(),
+        });
+    }
+
+    /// Remove, and return, the entry for `inner` within the named package.
+    pub fn forget(&mut self, package: &Name, inner: &InnerPackage) -> Option<LedgerEntry> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.is(package, inner))?;
+        Some(self.entries.remove(index))
+    }
+
+    /// Write the ledger back to its file, if it has one.
+    pub fn save(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            debug!("Ledger::save() to {:?}", path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, toml::to_string(self)?)?;
+        }
+        Ok(())
+    }
+}
+
+impl LedgerEntry {
+    fn is(&self, package: &Name, inner: &InnerPackage) -> bool {
+        &self.package == package && self.inner == inner.name
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[test]
+    fn test_record_and_forget() {
+        let temp = temp_dir();
+        let state_file = temp.path().join(STATE_FILE_NAME);
+        let package: Name = "Rust".parse().unwrap();
+        let inner: InnerPackage =
+            toml::from_str("name = \"fd\"\ninstaller = [\"system\", \"cargo\"]\n").unwrap();
+
+        let mut ledger = Ledger::load(&state_file).unwrap();
+        assert_eq!(ledger.entries().count(), 0);
+        ledger.record(&package, &inner, &"cargo".parse().unwrap(), "fd-find");
+        ledger.record(&package, &inner, &"cargo".parse().unwrap(), "fd-find");
+        ledger.save().unwrap();
+
+        let mut ledger = Ledger::load(&state_file).unwrap();
+        assert_eq!(ledger.entries().count(), 1);
+        let entry = ledger.find(&"rust".parse().unwrap(), &inner).unwrap();
+        assert_eq!(entry.installer.to_string(), "cargo");
+        assert_eq!(entry.name, "fd-find");
+
+        let changed: InnerPackage =
+            toml::from_str("name = \"fd\"\ninstaller = [\"brew\", \"cargo\"]\n").unwrap();
+        let entry = ledger.find(&package, &changed).unwrap();
+        assert_eq!(entry.installer.to_string(), "cargo");
+        assert_eq!(entry.installers, inner.installer);
+        assert!(ledger.forget(&package, &inner).is_some());
+        assert!(ledger.find(&package, &inner).is_none());
+    }
+}
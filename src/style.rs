@@ -0,0 +1,205 @@
+/*!
+Shared styling for human-readable output, so that headings, warnings, and installer names look
+the same in every command and no escape codes are written when color is unwanted.
+
+Whether color is used is decided by a `ColorChoice`; with `Auto` color is only used when the
+output stream is a terminal and the `NO_COLOR` environment variable is not set (see
+<https://no-color.org>).
+
+# Example
+
+```rust
+use packpack::style::{ColorChoice, Styler};
+
+let styler = Styler::new(ColorChoice::Never.for_stdout());
+assert_eq!(styler.heading("Packages"), "Packages");
+```
+
+*/
+
+use ansi_term::{Colour, Style};
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::fmt::{Display, Formatter};
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// Whether to use color; `Auto` will use color only for terminals, and not if `NO_COLOR` is set.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+///
+/// Applies the shared styles to strings, or leaves them untouched if color is not enabled.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Styler {
+    colored: bool,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Display for ColorChoice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ColorChoice::Auto => "auto",
+                ColorChoice::Always => "always",
+                ColorChoice::Never => "never",
+            }
+        )
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!("invalid value '{}' for color choice", s)),
+        }
+    }
+}
+
+impl ColorChoice {
+    /// Should color be used when writing to standard output.
+    pub fn for_stdout(self) -> bool {
+        self.enabled(std::io::stdout().is_terminal())
+    }
+
+    /// Should color be used when writing to standard error.
+    pub fn for_stderr(self) -> bool {
+        self.enabled(std::io::stderr().is_terminal())
+    }
+
+    /// Should color be used when writing to a stream, given whether it is a terminal.
+    pub fn enabled(self, is_terminal: bool) -> bool {
+        colors_enabled(self, std::env::var_os("NO_COLOR"), is_terminal)
+    }
+}
+
+impl Styler {
+    pub fn new(colored: bool) -> Self {
+        Self { colored }
+    }
+
+    /// A styler that never adds color.
+    pub fn plain() -> Self {
+        Self::new(false)
+    }
+
+    pub fn is_colored(&self) -> bool {
+        self.colored
+    }
+
+    /// Section headings, such as the fields of an inspected package.
+    pub fn heading<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        self.paint(Style::new().bold(), s)
+    }
+
+    /// Warnings, and the summary of problems found.
+    pub fn warning<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        self.paint(Colour::Yellow.bold(), s)
+    }
+
+    /// The `error:` prefix on errors.
+    pub fn error<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        self.paint(Colour::Red.bold(), s)
+    }
+
+    /// The names, or identifiers, of installers.
+    pub fn installer<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        self.paint(Colour::Cyan.normal(), s)
+    }
+
+    /// Secondary detail, such as the layer a package belongs to.
+    pub fn dimmed<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        self.paint(Style::new().dimmed(), s)
+    }
+
+    fn paint<'a>(&self, style: Style, s: &'a str) -> Cow<'a, str> {
+        if self.colored {
+            style.paint(s).to_string().into()
+        } else {
+            s.into()
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+fn colors_enabled(choice: ColorChoice, no_color: Option<OsString>, is_terminal: bool) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => is_terminal && no_color.map(|value| value.is_empty()).unwrap_or(true),
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    #[test]
+    fn test_color_choice() {
+        for choice in &["auto", "always", "never"] {
+            assert_eq!(&choice.parse::<ColorChoice>().unwrap().to_string(), choice);
+        }
+        assert!("sometimes".parse::<ColorChoice>().is_err());
+
+        assert!(colors_enabled(ColorChoice::Auto, None, true));
+        assert!(!colors_enabled(ColorChoice::Auto, None, false));
+        assert!(!colors_enabled(
+            ColorChoice::Auto,
+            Some(OsString::from("1")),
+            true
+        ));
+        assert!(colors_enabled(
+            ColorChoice::Auto,
+            Some(OsString::new()),
+            true
+        ));
+        assert!(colors_enabled(
+            ColorChoice::Always,
+            Some(OsString::from("1")),
+            false
+        ));
+        assert!(!colors_enabled(ColorChoice::Never, None, true));
+    }
+
+    #[test]
+    fn test_styler() {
+        let plain = Styler::plain();
+        assert_eq!(plain.heading("Name"), "Name");
+        assert_eq!(plain.installer("homebrew"), "homebrew");
+
+        let colored = Styler::new(true);
+        assert_eq!(colored.heading("Name"), "\u{1b}[1mName\u{1b}[0m");
+        assert_ne!(colored.warning("careful"), "careful");
+    }
+}
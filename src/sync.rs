@@ -0,0 +1,490 @@
+/*!
+Keeps the package root in step with other machines when it is a git repository.
+
+Syncing fetches from the current branch's upstream, which may be any remote git understands
+including a `file://` URL or a local bare repository, and compares each incoming `package.toml`
+with the one at `HEAD`. The result is a `SyncPlan` listing the packages, and inner packages, that
+have been added or removed; applying the plan deletes what was removed, fast-forwards the
+repository, and then installs what was added. Committing records any local edits to the package
+root, such as those made by the `new`, `add`, and `remove` commands, so they may be pushed.
+
+The `git` command is used, rather than a library, so that the user's own git configuration,
+credentials, and hooks all apply.
+
+# Example
+
+```rust,no_run
+use packpack::config::current_configuration;
+use packpack::sync::Repository;
+use packpack::Options;
+
+let config = current_configuration().as_ref().unwrap();
+let repository = Repository::open(config).unwrap();
+let plan = repository.plan().unwrap();
+for change in &plan.changes {
+    println!("{}", change);
+}
+repository.apply(config, &plan, &Options { dry_run: false }).unwrap();
+```
+
+*/
+
+use crate::config::Configuration;
+use crate::error::{ErrorKind, Result};
+use crate::name::{Name, QualifiedName, QualifiedPattern};
+use crate::package::{
+    get_package_dir, InnerPackage, InstallerChain, PackageOverlay, PACKAGE_FILE_NAME,
+};
+use crate::selection::{Scope, Selection};
+use crate::{Installable, Options};
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// The package root, when it is within a git repository.
+///
+#[derive(Clone, Debug)]
+pub struct Repository {
+    path: PathBuf,
+}
+
+///
+/// The changes to packages that syncing with the upstream repository would make.
+///
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SyncPlan {
+    pub changes: Vec<PackageChange>,
+}
+
+///
+/// A single package that has been added, removed, or has had its inner packages changed.
+///
+#[derive(Clone, Debug, Serialize)]
+pub struct PackageChange {
+    pub name: Name,
+    pub kind: ChangeKind,
+    /// Inner packages to install, every inner package if the package was added.
+    pub install: Vec<InnerChange>,
+    /// Inner packages to delete, every inner package if the package was removed.
+    pub delete: Vec<InnerChange>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct InnerChange {
+    pub name: Name,
+    pub installer: InstallerChain,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl Repository {
+    ///
+    /// Open the repository containing the configured package root; it is an error if the package
+    /// root is not within a git repository.
+    ///
+    pub fn open(config: &Configuration) -> Result<Self> {
+        let path = get_package_dir(config)?;
+        info!("Repository::open({:?})", path);
+        let repository = Self { path };
+        match repository.git(&["rev-parse", "--is-inside-work-tree"]) {
+            Ok(output) if output.trim() == "true" => Ok(repository),
+            _ => Err(ErrorKind::NotARepository(repository.path).into()),
+        }
+    }
+
+    /// The package root this repository was opened for.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    ///
+    /// Fetch from the upstream of the current branch and return the changes to packages that
+    /// merging it would make. Nothing in the working tree is changed.
+    ///
+    pub fn plan(&self) -> Result<SyncPlan> {
+        info!("Repository::plan(..)");
+        let _ = self.git(&["fetch", "--quiet"])?;
+        let upstream = self.git(&["rev-parse", "--abbrev-ref", "@{upstream}"])?;
+        debug!("upstream is {:?}", upstream.trim());
+        self.check_fast_forward()?;
+
+        let diff = self.git(&[
+            "diff",
+            "--name-status",
+            "--no-renames",
+            "--relative",
+            "HEAD",
+            "@{upstream}",
+        ])?;
+        let mut changes: Vec<PackageChange> = Default::default();
+        for line in diff.lines() {
+            let mut parts = line.splitn(2, '\t');
+            let (status, file) = match (parts.next(), parts.next()) {
+                (Some(status), Some(file)) if is_package_file(file) => (status, file),
+                _ => continue,
+            };
+            trace!("> incoming {} {}", status, file);
+            let before = match status {
+                "A" => None,
+                _ => Some(self.package_at("HEAD", file)?),
+            };
+            let after = match status {
+                "D" => None,
+                _ => Some(self.package_at("@{upstream}", file)?),
+            };
+            if let Some(change) = PackageChange::between(before.as_ref(), after.as_ref()) {
+                changes.push(change);
+            }
+        }
+        changes.sort_by_key(|change| change.name.to_string().to_lowercase());
+        Ok(SyncPlan { changes })
+    }
+
+    ///
+    /// Apply `plan`; delete the removed packages and inner packages, fast-forward to the upstream,
+    /// and then install the added packages and inner packages. Nothing is deleted unless the
+    /// working tree is clean and the fast-forward is possible. With `dry_run` set the repository
+    /// is not changed, and the actions only report what they would do.
+    ///
+    pub fn apply(&self, config: &Configuration, plan: &SyncPlan, options: &Options) -> Result<()> {
+        info!("Repository::apply(.., {:?})", options);
+        if !self.git(&["status", "--porcelain"])?.trim().is_empty() {
+            return Err(ErrorKind::RepositoryNotClean(self.path.clone()).into());
+        }
+        self.check_fast_forward()?;
+        let deletes = plan.selection(|change| &change.delete);
+        if let Some(selection) = &deletes {
+            selection.delete(config, options)?;
+        }
+
+        if options.dry_run {
+            info!("SKIPPING merge of upstream changes");
+        } else {
+            let _ = self.git(&["merge", "--ff-only", "--quiet", "@{upstream}"])?;
+        }
+
+        if let Some(selection) = plan.selection(|change| &change.install) {
+            if options.dry_run {
+                // The added packages are not on disk until the merge.
+                info!("SKIPPING install of {:?}", selection.scope());
+            } else {
+                selection.install(config, options)?;
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Commit all local changes in the package root, with `message` or else one listing the
+    /// packages changed. Returns the message used, or `None` if there was nothing to commit.
+    ///
+    pub fn commit(&self, message: Option<&str>) -> Result<Option<String>> {
+        info!("Repository::commit({:?})", message);
+        let _ = self.git(&["add", "--all", "."])?;
+        let staged = self.git(&["diff", "--cached", "--name-only", "--relative"])?;
+        let mut packages: Vec<&str> = staged
+            .lines()
+            .filter_map(|file| file.split('/').next())
+            .collect();
+        packages.dedup();
+        if packages.is_empty() {
+            return Ok(None);
+        }
+        let message = match message {
+            Some(message) => message.to_string(),
+            None => format!("Update packages: {}", packages.join(", ")),
+        };
+        let _ = self.git(&["commit", "--quiet", "--message", &message])?;
+        Ok(Some(message))
+    }
+
+    ///
+    /// The package file at `revision`, read as an overlay so that packages which extend another
+    /// need not be complete; only the name and inner packages are compared.
+    ///
+    fn package_at(&self, revision: &str, file: &str) -> Result<PackageOverlay> {
+        let content = self.git(&["show", &format!("{}:./{}", revision, file)])?;
+        let package: PackageOverlay = toml::from_str(&content)?;
+        if package.name.is_none() {
+            return Err(ErrorKind::InvalidConfigFile(
+                PathBuf::from(file),
+                None,
+                "missing field `name`".to_string(),
+            )
+            .into());
+        }
+        Ok(package)
+    }
+
+    fn check_fast_forward(&self) -> Result<()> {
+        if self
+            .git(&["merge-base", "--is-ancestor", "HEAD", "@{upstream}"])
+            .is_err()
+        {
+            return Err(ErrorKind::RepositoryDiverged(self.path.clone()).into());
+        }
+        Ok(())
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String> {
+        trace!("> git {:?} in {:?}", args, self.path);
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.path)
+            .args(args)
+            .output()?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        } else {
+            let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            debug!("git {:?} failed: {}", args, message);
+            Err(ErrorKind::GitCommandFailed(args.join(" "), message).into())
+        }
+    }
+}
+
+impl SyncPlan {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    fn selection<F>(&self, inner: F) -> Option<Selection>
+    where
+        F: Fn(&PackageChange) -> &Vec<InnerChange>,
+    {
+        let patterns: Vec<QualifiedPattern> = self
+            .changes
+            .iter()
+            .flat_map(|change| {
+                inner(change).iter().map(move |inner| {
+                    QualifiedName {
+                        layer: None,
+                        name: change.name.clone(),
+                        inner: Some(inner.name.clone()),
+                    }
+                    .into()
+                })
+            })
+            .collect();
+        if patterns.is_empty() {
+            None
+        } else {
+            Some(Selection::new(Scope::Packages(patterns)))
+        }
+    }
+}
+
+impl PackageChange {
+    fn between(before: Option<&PackageOverlay>, after: Option<&PackageOverlay>) -> Option<Self> {
+        let inner = |package: Option<&PackageOverlay>| -> Vec<InnerChange> {
+            package
+                .map(|package| package.packages.iter().map(InnerChange::from).collect())
+                .unwrap_or_default()
+        };
+        let (name, kind) = match (before, after) {
+            (None, Some(after)) => (after.name.clone()?, ChangeKind::Added),
+            (Some(before), None) => (before.name.clone()?, ChangeKind::Removed),
+            (Some(_), Some(after)) => (after.name.clone()?, ChangeKind::Modified),
+            (None, None) => return None,
+        };
+        let (before, after) = (inner(before), inner(after));
+        let change = Self {
+            name,
+            kind,
+            install: after
+                .iter()
+                .filter(|inner| !before.contains(inner))
+                .cloned()
+                .collect(),
+            delete: before
+                .iter()
+                .filter(|inner| !after.contains(inner))
+                .cloned()
+                .collect(),
+        };
+        if change.kind == ChangeKind::Modified
+            && change.install.is_empty()
+            && change.delete.is_empty()
+        {
+            None
+        } else {
+            Some(change)
+        }
+    }
+}
+
+impl Display for PackageChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let list = |inner: &[InnerChange]| {
+            inner
+                .iter()
+                .map(|inner| inner.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        match self.kind {
+            ChangeKind::Added => write!(f, "+ {} (install {})", self.name, list(&self.install)),
+            ChangeKind::Removed => write!(f, "- {} (delete {})", self.name, list(&self.delete)),
+            ChangeKind::Modified => {
+                write!(f, "~ {}", self.name)?;
+                if !self.install.is_empty() {
+                    write!(f, " (install {})", list(&self.install))?;
+                }
+                if !self.delete.is_empty() {
+                    write!(f, " (delete {})", list(&self.delete))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl From<&InnerPackage> for InnerChange {
+    fn from(inner: &InnerPackage) -> Self {
+        Self {
+            name: inner.name.clone(),
+            installer: inner.installer.clone(),
+        }
+    }
+}
+
+impl Display for InnerChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.name, self.installer)
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Private Functions
+// ------------------------------------------------------------------------------------------------
+
+/// Only files directly within a package directory are package definitions.
+fn is_package_file(file: &str) -> bool {
+    let parts: Vec<&str> = file.split('/').collect();
+    parts.len() == 2 && parts[1] == PACKAGE_FILE_NAME
+}
+
+// ------------------------------------------------------------------------------------------------
+// Unit Tests
+// ------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigurationBuilder;
+    use crate::testing::{temp_dir, write_package};
+    #[allow(unused_imports)]
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    fn run_git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    fn package_content(name: &str, inner: &[&str]) -> String {
+        let mut content = format!("name = \"{}\"\nlayer = \"shell\"\npackages = [\n", name);
+        for inner in inner {
+            content.push_str(&format!(
+                "  {{ name = \"{}\", installer = \"cargo\" }},\n",
+                inner
+            ));
+        }
+        content.push_str("]\n");
+        content
+    }
+
+    #[test]
+    fn test_plan_and_commit() {
+        let temp = temp_dir();
+        let root = temp.path();
+        let (remote, here, there) = (
+            root.join("remote.git"),
+            root.join("here"),
+            root.join("there"),
+        );
+        run_git(root, &["init", "--quiet", "--bare", "remote.git"]);
+        run_git(
+            root,
+            &["clone", "--quiet", remote.to_str().unwrap(), "there"],
+        );
+        run_git(&there, &["config", "user.name", "test"]);
+        run_git(&there, &["config", "user.email", "test@example.com"]);
+        let _ = write_package(&there, "Fish", &package_content("Fish", &["fish"]));
+        let _ = write_package(&there, "Tools", &package_content("Tools", &["bat", "fd"]));
+        run_git(&there, &["add", "."]);
+        run_git(&there, &["commit", "--quiet", "-m", "initial"]);
+        run_git(&there, &["push", "--quiet", "origin", "HEAD"]);
+        run_git(
+            root,
+            &["clone", "--quiet", remote.to_str().unwrap(), "here"],
+        );
+
+        let _ = write_package(
+            &there,
+            "Tools",
+            &package_content("Tools", &["bat", "ripgrep"]),
+        );
+        std::fs::remove_dir_all(there.join("Fish")).unwrap();
+        let _ = write_package(&there, "Zsh", &package_content("Zsh", &["zsh"]));
+        let config = ConfigurationBuilder::new()
+            .package_dir(&there)
+            .build()
+            .unwrap();
+        let repository = Repository::open(&config).unwrap();
+        let message = repository.commit(None).unwrap();
+        assert_eq!(
+            message,
+            Some("Update packages: Fish, Tools, Zsh".to_string())
+        );
+        assert_eq!(repository.commit(None).unwrap(), None);
+        run_git(&there, &["push", "--quiet", "origin", "HEAD"]);
+
+        let config = ConfigurationBuilder::new()
+            .package_dir(&here)
+            .build()
+            .unwrap();
+        let repository = Repository::open(&config).unwrap();
+        let plan = repository.plan().unwrap();
+        let summary: Vec<String> = plan.changes.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            summary,
+            vec![
+                "- Fish (delete fish (cargo))",
+                "~ Tools (install ripgrep (cargo)) (delete fd (cargo))",
+                "+ Zsh (install zsh (cargo))",
+            ]
+        );
+
+        repository
+            .apply(&config, &plan, &Options { dry_run: true })
+            .unwrap();
+        assert!(here.join("Fish").is_dir());
+
+        std::fs::write(here.join("notes.txt"), "uncommitted").unwrap();
+        assert!(repository
+            .apply(&config, &plan, &Options { dry_run: false })
+            .is_err());
+        assert!(here.join("Fish").is_dir());
+    }
+}
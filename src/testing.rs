@@ -0,0 +1,104 @@
+/*!
+Fixtures shared by the unit tests of more than one module.
+*/
+
+use crate::config::{Configuration, ConfigurationBuilder};
+use crate::package::PACKAGE_FILE_NAME;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+// ------------------------------------------------------------------------------------------------
+// Public Types
+// ------------------------------------------------------------------------------------------------
+
+///
+/// A package root in a new temporary directory, removed with its content when dropped, for
+/// tests that write their own package files.
+///
+#[derive(Debug)]
+pub(crate) struct TestPackageRoot {
+    temp: TempDir,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Public Functions
+// ------------------------------------------------------------------------------------------------
+
+/// The package root of example packages within this repository.
+pub(crate) fn test_package_root() -> PathBuf {
+    PathBuf::from(format!("{}/test-pkgs", env!("CARGO_MANIFEST_DIR")))
+}
+
+///
+/// The built-in configuration, for Linux whatever the running platform, with the example
+/// packages as its only package root.
+///
+pub(crate) fn test_configuration() -> Configuration {
+    ConfigurationBuilder::new()
+        .target_os("linux".parse().unwrap())
+        .package_dir(&test_package_root())
+        .build()
+        .unwrap()
+}
+
+///
+/// A new, empty, directory that is removed, with its content, when the returned value is
+/// dropped; including when a test fails.
+///
+pub(crate) fn temp_dir() -> TempDir {
+    tempfile::Builder::new()
+        .prefix("packpack-")
+        .tempdir()
+        .unwrap()
+}
+
+///
+/// Write `content` as the package file in the directory `directory` of the package root `root`,
+/// creating the directory if necessary, and return the file's path.
+///
+pub(crate) fn write_package(root: &Path, directory: &str, content: &str) -> PathBuf {
+    let package_dir = root.join(directory);
+    std::fs::create_dir_all(&package_dir).unwrap();
+    let package_file = package_dir.join(PACKAGE_FILE_NAME);
+    std::fs::write(&package_file, content).unwrap();
+    package_file
+}
+
+// ------------------------------------------------------------------------------------------------
+// Implementations
+// ------------------------------------------------------------------------------------------------
+
+impl TestPackageRoot {
+    pub(crate) fn new() -> Self {
+        Self { temp: temp_dir() }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        self.temp.path()
+    }
+
+    /// Write `content` as the package file in the directory `directory` of this root.
+    pub(crate) fn write_package(&self, directory: &str, content: &str) -> PathBuf {
+        write_package(self.path(), directory, content)
+    }
+
+    /// A builder with this as its only package root.
+    pub(crate) fn builder(&self) -> ConfigurationBuilder {
+        ConfigurationBuilder::new().package_dir(self.path())
+    }
+
+    ///
+    /// A builder with the example packages and then this as package roots, so that packages
+    /// written here override, or extend, the examples.
+    ///
+    pub(crate) fn builder_over_examples(&self) -> ConfigurationBuilder {
+        ConfigurationBuilder::new()
+            .package_dir(&test_package_root())
+            .package_dir(self.path())
+    }
+
+    /// The configuration with this as its only package root.
+    pub(crate) fn configuration(&self) -> Configuration {
+        self.builder().build().unwrap()
+    }
+}